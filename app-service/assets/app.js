@@ -6,11 +6,18 @@ logoutLink.addEventListener("click", (e) => {
     e.preventDefault();
 
     let url = logoutLink.href;
+    // The auth service only accepts the cookie with the CSRF token it hands out
+    let csrfUrl = url.replace(/\/logout$/, "/csrf");
 
-    fetch(url, {
+    fetch(csrfUrl, {
+        credentials: 'include',
+    }).then(response => response.json()).then(data => fetch(url, {
         method: 'POST',
         credentials: 'include', // This will include cookies in the request
-    }).then(response => {
+        headers: {
+            'X-CSRF-Token': data.csrfToken,
+        },
+    })).then(response => {
         if (response.ok) {
             loginLink.style.display = "block";
             logoutLink.style.display = "none";
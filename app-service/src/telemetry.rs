@@ -0,0 +1,54 @@
+use opentelemetry::{global, trace::TracerProvider as _, KeyValue};
+use opentelemetry_sdk::{
+    propagation::TraceContextPropagator,
+    runtime,
+    trace::{self as sdktrace, TracerProvider},
+    Resource,
+};
+use std::env;
+use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+
+const SERVICE_NAME: &str = "app-service";
+
+// Same setup as the auth service: logs to stdout, exports spans when
+// `OTEL_EXPORTER_OTLP_ENDPOINT` is set, and sends `traceparent` along with the verify calls.
+pub fn init_tracing() -> Result<(), Box<dyn std::error::Error>> {
+    let filter_layer = EnvFilter::try_from_default_env().or_else(|_| EnvFilter::try_new("info"))?;
+
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    let otel_layer = tracing_opentelemetry::layer().with_tracer(init_tracer()?);
+
+    tracing_subscriber::registry()
+        .with(filter_layer)
+        .with(fmt::layer().compact())
+        .with(otel_layer)
+        .init();
+
+    Ok(())
+}
+
+fn init_tracer() -> Result<sdktrace::Tracer, Box<dyn std::error::Error>> {
+    let service_name = env::var("OTEL_SERVICE_NAME").unwrap_or(SERVICE_NAME.to_owned());
+    let config = sdktrace::config()
+        .with_resource(Resource::new([KeyValue::new("service.name", service_name)]));
+
+    let export = env::var("OTEL_EXPORTER_OTLP_ENDPOINT").is_ok_and(|endpoint| !endpoint.is_empty());
+    if !export {
+        let provider = TracerProvider::builder().with_config(config).build();
+        let tracer = provider.tracer(SERVICE_NAME);
+        global::set_tracer_provider(provider);
+        return Ok(tracer);
+    }
+
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(opentelemetry_otlp::new_exporter().tonic())
+        .with_trace_config(config)
+        .install_batch(runtime::Tokio)?;
+
+    Ok(tracer)
+}
+
+pub fn shutdown_tracing() {
+    global::shutdown_tracer_provider();
+}
@@ -0,0 +1,121 @@
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+// Upper bound on cached tokens so a flood of distinct tokens can't grow it forever
+const MAX_ENTRIES: usize = 10_000;
+
+struct CachedClaims {
+    claims: Value,
+    expires_at: Instant,
+}
+
+// Remembers the claims of recently verified tokens for a short while, so a burst of
+// requests with the same token is verified once. Only valid tokens are cached.
+pub struct ClaimsCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, CachedClaims>>,
+}
+
+impl ClaimsCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, token: &str) -> Option<Value> {
+        let mut entries = self.entries.lock().unwrap();
+
+        match entries.get(token) {
+            Some(cached) if cached.expires_at > Instant::now() => Some(cached.claims.clone()),
+            Some(_) => {
+                entries.remove(token);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, token: &str, claims: &Value) {
+        if self.ttl.is_zero() {
+            return;
+        }
+
+        // Never keep a token around after it has expired
+        let ttl = match claims["exp"].as_u64().map(time_until) {
+            Some(Some(remaining)) => remaining.min(self.ttl),
+            Some(None) => return,
+            None => self.ttl,
+        };
+
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= MAX_ENTRIES {
+            entries.retain(|_, cached| cached.expires_at > now);
+            if entries.len() >= MAX_ENTRIES {
+                entries.clear();
+            }
+        }
+
+        entries.insert(
+            token.to_owned(),
+            CachedClaims {
+                claims: claims.clone(),
+                expires_at: now + ttl,
+            },
+        );
+    }
+}
+
+// Time left until the given unix timestamp, if it's still in the future
+fn time_until(timestamp: u64) -> Option<Duration> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?;
+    Duration::from_secs(timestamp)
+        .checked_sub(now)
+        .filter(|remaining| !remaining.is_zero())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn exp_in(seconds: u64) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            + seconds
+    }
+
+    #[test]
+    fn returns_claims_until_ttl_elapses() {
+        let cache = ClaimsCache::new(Duration::from_millis(50));
+        let claims = json!({ "sub": "user@example.com", "exp": exp_in(60) });
+
+        cache.insert("token", &claims);
+        assert_eq!(cache.get("token"), Some(claims));
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(cache.get("token"), None);
+    }
+
+    #[test]
+    fn does_not_cache_expired_tokens_or_when_disabled() {
+        let cache = ClaimsCache::new(Duration::from_secs(30));
+        cache.insert("expired", &json!({ "sub": "user@example.com", "exp": 1 }));
+        assert_eq!(cache.get("expired"), None);
+
+        let cache = ClaimsCache::new(Duration::ZERO);
+        cache.insert(
+            "token",
+            &json!({ "sub": "user@example.com", "exp": exp_in(60) }),
+        );
+        assert_eq!(cache.get("token"), None);
+    }
+}
@@ -0,0 +1,313 @@
+use crate::{
+    cache::ClaimsCache,
+    jwks::JwksVerifier,
+    remote::{parse_env, RemoteSettings, RemoteVerifier},
+    AuthError, TokenSource,
+};
+use jsonwebtoken::{decode, DecodingKey, Validation};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::{env, sync::Arc, time::Duration};
+
+const DEFAULT_CACHE_TTL_MILLISECONDS: u64 = 5_000;
+const DEFAULT_JWKS_REFRESH_SECONDS: u64 = 300;
+const DEFAULT_JWKS_TIMEOUT_MILLISECONDS: u64 = 2_000;
+
+// How tokens are checked
+#[derive(Debug, Clone)]
+pub enum Verification {
+    // Locally, with the secret shared with the auth service (HS256)
+    Secret(String),
+    // Locally, with the keys published at a JWKS url, refetched every refresh_interval
+    Jwks {
+        url: String,
+        refresh_interval: Duration,
+    },
+    // By asking the auth service. The only mode that sees tokens banned on logout.
+    Remote(RemoteSettings),
+}
+
+#[derive(Debug, Clone)]
+pub struct AuthClientConfig {
+    pub token_source: TokenSource,
+    pub verification: Verification,
+    // How long verified tokens are trusted without checking them again, zero disables caching
+    pub cache_ttl: Duration,
+}
+
+impl AuthClientConfig {
+    pub fn new(verification: Verification) -> Self {
+        Self {
+            token_source: TokenSource::default(),
+            verification,
+            cache_ttl: Duration::from_millis(DEFAULT_CACHE_TTL_MILLISECONDS),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let verification = match env::var("AUTH_VERIFICATION")
+            .unwrap_or("remote".to_owned())
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "remote" => Verification::Remote(RemoteSettings::from_env()),
+            "secret" => Verification::Secret(
+                env::var("JWT_SECRET").expect("JWT_SECRET must be set to verify tokens locally"),
+            ),
+            "jwks" => Verification::Jwks {
+                url: env::var("AUTH_JWKS_URL").expect("AUTH_JWKS_URL must be set to use JWKS"),
+                refresh_interval: Duration::from_secs(parse_env(
+                    "AUTH_JWKS_REFRESH_SECS",
+                    DEFAULT_JWKS_REFRESH_SECONDS,
+                )),
+            },
+            other => panic!(
+                "Invalid AUTH_VERIFICATION: {}. Expected remote, secret or jwks",
+                other
+            ),
+        };
+
+        Self {
+            token_source: parse_env("AUTH_TOKEN_SOURCE", TokenSource::default()),
+            verification,
+            cache_ttl: Duration::from_millis(parse_env(
+                "AUTH_CACHE_TTL_MS",
+                DEFAULT_CACHE_TTL_MILLISECONDS,
+            )),
+        }
+    }
+}
+
+enum Verifier {
+    Secret(DecodingKey),
+    Jwks(JwksVerifier),
+    Remote(RemoteVerifier),
+}
+
+struct Inner {
+    token_source: TokenSource,
+    verifier: Verifier,
+    cache: ClaimsCache,
+}
+
+// Verifies tokens issued by the auth service. Add it to the router state to use the
+// `AuthenticatedUser` extractor; clones are cheap and share connections and cache.
+#[derive(Clone)]
+pub struct AuthClient {
+    inner: Arc<Inner>,
+}
+
+impl AuthClient {
+    pub fn new(config: AuthClientConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        let verifier = match config.verification {
+            Verification::Secret(secret) => {
+                Verifier::Secret(DecodingKey::from_secret(secret.as_bytes()))
+            }
+            Verification::Jwks {
+                url,
+                refresh_interval,
+            } => Verifier::Jwks(JwksVerifier::new(
+                url,
+                Duration::from_millis(DEFAULT_JWKS_TIMEOUT_MILLISECONDS),
+                refresh_interval,
+            )?),
+            Verification::Remote(settings) => Verifier::Remote(RemoteVerifier::new(settings)?),
+        };
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                token_source: config.token_source,
+                verifier,
+                cache: ClaimsCache::new(config.cache_ttl),
+            }),
+        })
+    }
+
+    pub fn token_source(&self) -> &TokenSource {
+        &self.inner.token_source
+    }
+
+    // Verifies the token and returns its claims as the given type
+    pub async fn verify<C: DeserializeOwned>(&self, token: &str) -> Result<C, AuthError> {
+        let claims = match self.inner.cache.get(token) {
+            Some(claims) => claims,
+            None => {
+                let claims = self.verify_uncached(token).await?;
+                self.inner.cache.insert(token, &claims);
+                claims
+            }
+        };
+
+        serde_json::from_value(claims).map_err(|_| AuthError::InvalidToken)
+    }
+
+    async fn verify_uncached(&self, token: &str) -> Result<Value, AuthError> {
+        match &self.inner.verifier {
+            Verifier::Secret(key) => {
+                // No leeway, like the auth service, which rejects a token as soon as it expires
+                let mut validation = Validation::default();
+                validation.leeway = 0;
+
+                decode::<Value>(token, key, &validation)
+                    .map(|data| data.claims)
+                    .map_err(|_| AuthError::InvalidToken)
+            }
+            Verifier::Jwks(verifier) => verifier.verify(token).await,
+            Verifier::Remote(verifier) => match verifier.verify_token(token).await? {
+                true => read_claims(token),
+                false => Err(AuthError::InvalidToken),
+            },
+        }
+    }
+}
+
+// Reads the claims of a token the auth service already vouched for
+fn read_claims(token: &str) -> Result<Value, AuthError> {
+    let mut validation = Validation::default();
+    validation.insecure_disable_signature_validation();
+
+    decode::<Value>(token, &DecodingKey::from_secret(&[]), &validation)
+        .map(|data| data.claims)
+        .map_err(|_| AuthError::InvalidToken)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{remote::AuthTransport, Claims};
+    use axum::{http::StatusCode, routing::get, routing::post, Json, Router};
+    use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+    use serde_json::json;
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    const SECRET: &str = "secret";
+
+    fn claims(exp_in_seconds: i64) -> Claims {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        Claims {
+            sub: "user@example.com".to_owned(),
+            exp: (now + exp_in_seconds) as usize,
+        }
+    }
+
+    fn token(header: &Header, claims: &Claims) -> String {
+        encode(header, claims, &EncodingKey::from_secret(SECRET.as_bytes())).unwrap()
+    }
+
+    async fn serve(router: Router) -> u16 {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        port
+    }
+
+    #[tokio::test]
+    async fn verifies_tokens_with_shared_secret() {
+        let client = AuthClient::new(AuthClientConfig::new(Verification::Secret(
+            SECRET.to_owned(),
+        )))
+        .unwrap();
+        let claims = claims(600);
+
+        let verified: Claims = client
+            .verify(&token(&Header::default(), &claims))
+            .await
+            .unwrap();
+        assert_eq!(verified, claims);
+
+        let expired = token(&Header::default(), &self::claims(-600));
+        assert!(matches!(
+            client.verify::<Claims>(&expired).await,
+            Err(AuthError::InvalidToken)
+        ));
+        let just_expired = token(&Header::default(), &self::claims(-5));
+        assert!(matches!(
+            client.verify::<Claims>(&just_expired).await,
+            Err(AuthError::InvalidToken)
+        ));
+        assert!(matches!(
+            client.verify::<Claims>("not-a-token").await,
+            Err(AuthError::InvalidToken)
+        ));
+    }
+
+    #[tokio::test]
+    async fn verifies_tokens_with_jwks() {
+        // base64url of SECRET
+        let jwks =
+            json!({ "keys": [{ "kty": "oct", "kid": "key-1", "alg": "HS256", "k": "c2VjcmV0" }] });
+        let port = serve(Router::new().route("/jwks", get(|| async move { Json(jwks) }))).await;
+        let client = AuthClient::new(AuthClientConfig::new(Verification::Jwks {
+            url: format!("http://127.0.0.1:{}/jwks", port),
+            refresh_interval: Duration::from_secs(60),
+        }))
+        .unwrap();
+
+        let mut header = Header {
+            kid: Some("key-1".to_owned()),
+            ..Header::default()
+        };
+        let claims = claims(600);
+        let verified: Claims = client.verify(&token(&header, &claims)).await.unwrap();
+        assert_eq!(verified, claims);
+
+        header.kid = Some("unknown".to_owned());
+        assert!(matches!(
+            client.verify::<Claims>(&token(&header, &claims)).await,
+            Err(AuthError::InvalidToken)
+        ));
+
+        // Signed with the right key, but not with the algorithm the key declares
+        let header = Header {
+            kid: Some("key-1".to_owned()),
+            ..Header::new(Algorithm::HS512)
+        };
+        assert!(matches!(
+            client.verify::<Claims>(&token(&header, &claims)).await,
+            Err(AuthError::InvalidToken)
+        ));
+    }
+
+    #[tokio::test]
+    async fn caches_remote_verifications() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let port = serve(Router::new().route(
+            "/verify-token",
+            post(move || async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                StatusCode::OK
+            }),
+        ))
+        .await;
+
+        let client = AuthClient::new(AuthClientConfig::new(Verification::Remote(
+            RemoteSettings {
+                host: "127.0.0.1".to_owned(),
+                transport: AuthTransport::Http,
+                grpc_port: 0,
+                http_port: port,
+                timeout: Duration::from_millis(200),
+                max_retries: 0,
+            },
+        )))
+        .unwrap();
+
+        let claims = claims(600);
+        let token = token(&Header::default(), &claims);
+        for _ in 0..3 {
+            let verified: Claims = client.verify(&token).await.unwrap();
+            assert_eq!(verified, claims);
+        }
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
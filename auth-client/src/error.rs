@@ -0,0 +1,29 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuthError {
+    #[error("Missing auth token")]
+    MissingToken,
+    #[error("Invalid auth token")]
+    InvalidToken,
+    // The token could not be checked, e.g. the auth service or the JWKS endpoint is down
+    #[error("Could not verify token: {0}")]
+    Unavailable(String),
+}
+
+impl IntoResponse for AuthError {
+    fn into_response(self) -> Response {
+        match self {
+            AuthError::MissingToken | AuthError::InvalidToken => {
+                StatusCode::UNAUTHORIZED.into_response()
+            }
+            AuthError::Unavailable(_) => {
+                tracing::error!("{}", self);
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
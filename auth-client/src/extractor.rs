@@ -0,0 +1,94 @@
+use crate::{AuthClient, AuthError, Claims};
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts},
+    http::request::Parts,
+};
+use serde::de::DeserializeOwned;
+
+// Extracts and verifies the token of the request, rejecting it with 401 when it's missing
+// or invalid. Requires an `AuthClient` in the router state. Use your own claims type with
+// `AuthenticatedUser<MyClaims>` when the auth service adds extra claims.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedUser<C = Claims> {
+    pub claims: C,
+    pub token: String,
+}
+
+#[async_trait]
+impl<S, C> FromRequestParts<S> for AuthenticatedUser<C>
+where
+    AuthClient: FromRef<S>,
+    S: Send + Sync,
+    C: DeserializeOwned,
+{
+    type Rejection = AuthError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let auth_client = AuthClient::from_ref(state);
+        let token = auth_client
+            .token_source()
+            .extract(parts)
+            .ok_or(AuthError::MissingToken)?;
+        let claims = auth_client.verify(&token).await?;
+
+        Ok(Self { claims, token })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AuthClientConfig, TokenSource, Verification};
+    use axum::http::StatusCode;
+    use axum::{routing::get, Router};
+    use jsonwebtoken::{encode, EncodingKey, Header};
+
+    async fn protected(user: AuthenticatedUser) -> String {
+        user.claims.sub
+    }
+
+    #[tokio::test]
+    async fn rejects_requests_without_a_valid_token() {
+        let mut config = AuthClientConfig::new(Verification::Secret("secret".to_owned()));
+        config.token_source = TokenSource::Bearer;
+        let router = Router::new()
+            .route("/protected", get(protected))
+            .with_state(AuthClient::new(config).unwrap());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/protected", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        let token = encode(
+            &Header::default(),
+            &Claims {
+                sub: "user@example.com".to_owned(),
+                exp: usize::MAX / 2,
+            },
+            &EncodingKey::from_secret(b"secret"),
+        )
+        .unwrap();
+        let http_client = reqwest::Client::new();
+
+        let response = http_client
+            .get(&url)
+            .bearer_auth(token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.text().await.unwrap(), "user@example.com");
+
+        let response = http_client.get(&url).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = http_client
+            .get(&url)
+            .bearer_auth("invalid")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,162 @@
+use crate::AuthError;
+use jsonwebtoken::{
+    decode, decode_header,
+    jwk::{AlgorithmParameters, Jwk, JwkSet},
+    Algorithm, DecodingKey, Validation,
+};
+use serde_json::Value;
+use std::{
+    str::FromStr,
+    time::{Duration, Instant},
+};
+use tokio::sync::RwLock;
+
+// Keys are refetched at most this often when a token names a key id we don't know,
+// so forged tokens can't make us hammer the JWKS endpoint
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+struct FetchedKeys {
+    keys: JwkSet,
+    fetched_at: Instant,
+}
+
+// A key with the algorithms it may be used with. They come from the key, never from the
+// token header, which whoever sent the token picked.
+struct VerificationKey {
+    key: DecodingKey,
+    algorithms: Vec<Algorithm>,
+}
+
+// Verifies tokens locally with the public keys published at a JWKS url
+pub struct JwksVerifier {
+    url: String,
+    http_client: reqwest::Client,
+    refresh_interval: Duration,
+    keys: RwLock<Option<FetchedKeys>>,
+}
+
+impl JwksVerifier {
+    pub fn new(
+        url: String,
+        timeout: Duration,
+        refresh_interval: Duration,
+    ) -> Result<Self, AuthError> {
+        let http_client = reqwest::Client::builder()
+            .connect_timeout(timeout)
+            .timeout(timeout)
+            .build()
+            .map_err(|e| AuthError::Unavailable(e.to_string()))?;
+
+        Ok(Self {
+            url,
+            http_client,
+            refresh_interval,
+            keys: RwLock::new(None),
+        })
+    }
+
+    pub async fn verify(&self, token: &str) -> Result<Value, AuthError> {
+        let header = decode_header(token).map_err(|_| AuthError::InvalidToken)?;
+        let key = self.verification_key(header.kid.as_deref()).await?;
+        if !key.algorithms.contains(&header.alg) {
+            return Err(AuthError::InvalidToken);
+        }
+
+        decode::<Value>(token, &key.key, &Validation::new(header.alg))
+            .map(|data| data.claims)
+            .map_err(|_| AuthError::InvalidToken)
+    }
+
+    async fn verification_key(&self, kid: Option<&str>) -> Result<VerificationKey, AuthError> {
+        if let Some(key) = self.cached_key(kid, self.refresh_interval).await? {
+            return Ok(key);
+        }
+
+        let mut keys = self.keys.write().await;
+        // Another request may have refreshed the keys while we waited for the lock
+        let is_recent = keys
+            .as_ref()
+            .is_some_and(|fetched| fetched.fetched_at.elapsed() < MIN_REFRESH_INTERVAL);
+        if !is_recent {
+            *keys = Some(FetchedKeys {
+                keys: self.fetch_keys().await?,
+                fetched_at: Instant::now(),
+            });
+        }
+        drop(keys);
+
+        self.cached_key(kid, Duration::MAX)
+            .await?
+            .ok_or(AuthError::InvalidToken)
+    }
+
+    // Looks the key up in the cached set, as long as it's not older than max_age
+    async fn cached_key(
+        &self,
+        kid: Option<&str>,
+        max_age: Duration,
+    ) -> Result<Option<VerificationKey>, AuthError> {
+        let keys = self.keys.read().await;
+        let Some(fetched) = keys.as_ref() else {
+            return Ok(None);
+        };
+        if fetched.fetched_at.elapsed() >= max_age {
+            return Ok(None);
+        }
+
+        let jwk = match kid {
+            Some(kid) => fetched.keys.find(kid),
+            // Tokens without a key id are only accepted when there's a single key to pick
+            None if fetched.keys.keys.len() == 1 => fetched.keys.keys.first(),
+            None => None,
+        };
+
+        jwk.map(|jwk| {
+            let key = DecodingKey::from_jwk(jwk).map_err(|_| AuthError::InvalidToken)?;
+
+            Ok(VerificationKey {
+                key,
+                algorithms: algorithms(jwk),
+            })
+        })
+        .transpose()
+    }
+
+    async fn fetch_keys(&self) -> Result<JwkSet, AuthError> {
+        self.http_client
+            .get(&self.url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| AuthError::Unavailable(e.to_string()))?
+            .json::<JwkSet>()
+            .await
+            .map_err(|e| AuthError::Unavailable(e.to_string()))
+    }
+}
+
+// The algorithm the key declares, or any of its family when it doesn't. Keys declaring an
+// encryption algorithm can't verify anything.
+fn algorithms(jwk: &Jwk) -> Vec<Algorithm> {
+    if let Some(algorithm) = jwk.common.key_algorithm {
+        return Algorithm::from_str(&algorithm.to_string())
+            .into_iter()
+            .collect();
+    }
+
+    match jwk.algorithm {
+        AlgorithmParameters::OctetKey(_) => {
+            vec![Algorithm::HS256, Algorithm::HS384, Algorithm::HS512]
+        }
+        AlgorithmParameters::RSA(_) => vec![
+            Algorithm::RS256,
+            Algorithm::RS384,
+            Algorithm::RS512,
+            Algorithm::PS256,
+            Algorithm::PS384,
+            Algorithm::PS512,
+        ],
+        AlgorithmParameters::EllipticCurve(_) => vec![Algorithm::ES256, Algorithm::ES384],
+        AlgorithmParameters::OctetKeyPair(_) => vec![Algorithm::EdDSA],
+    }
+}
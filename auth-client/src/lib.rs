@@ -0,0 +1,25 @@
+// Client side of the auth service: verifies its tokens in other services, either locally
+// (shared secret or JWKS) or by asking the auth service, and exposes the result as the
+// `AuthenticatedUser` axum extractor.
+//
+// let auth_client = AuthClient::new(AuthClientConfig::from_env())?;
+// let app = Router::new()
+//     .route("/protected", get(|user: AuthenticatedUser| async move { user.claims.sub }))
+//     .with_state(auth_client);
+
+mod cache;
+mod client;
+mod error;
+mod extractor;
+mod jwks;
+mod remote;
+mod token_source;
+mod trace_context;
+
+// Re-exporting
+pub use auth_domain::Claims;
+pub use client::{AuthClient, AuthClientConfig, Verification};
+pub use error::AuthError;
+pub use extractor::AuthenticatedUser;
+pub use remote::{AuthTransport, RemoteSettings};
+pub use token_source::TokenSource;
@@ -0,0 +1,309 @@
+use crate::{trace_context, AuthError};
+use auth_proto::{auth_client::AuthClient as GrpcAuthClient, StatusCode, VerifyTokenRequest};
+use std::{env, str::FromStr, time::Duration};
+use tonic::{
+    transport::{Channel, Endpoint},
+    Code,
+};
+
+const DEFAULT_AUTH_SERVICE_HOST_NAME: &str = "0.0.0.0";
+const DEFAULT_GRPC_PORT: u16 = 50051;
+const DEFAULT_HTTP_PORT: u16 = 3000;
+const DEFAULT_TIMEOUT_MILLISECONDS: u64 = 500;
+const DEFAULT_MAX_RETRIES: u32 = 2;
+const RETRY_BACKOFF_MILLISECONDS: u64 = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthTransport {
+    // gRPC over a shared HTTP/2 channel, falling back to HTTP when it's unavailable
+    Grpc,
+    // JSON over HTTP only
+    Http,
+}
+
+impl FromStr for AuthTransport {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "grpc" => Ok(Self::Grpc),
+            "http" => Ok(Self::Http),
+            other => Err(format!(
+                "Invalid auth service transport: {}. Expected grpc or http",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RemoteSettings {
+    pub host: String,
+    pub transport: AuthTransport,
+    pub grpc_port: u16,
+    pub http_port: u16,
+    // Deadline of every single attempt
+    pub timeout: Duration,
+    // Extra attempts made when the auth service is unreachable or too slow
+    pub max_retries: u32,
+}
+
+impl RemoteSettings {
+    pub fn from_env() -> Self {
+        let host =
+            env::var("AUTH_SERVICE_HOST_NAME").unwrap_or(DEFAULT_AUTH_SERVICE_HOST_NAME.to_owned());
+        let transport = env::var("AUTH_SERVICE_TRANSPORT")
+            .map(|transport| {
+                transport.parse().unwrap_or_else(|_| {
+                    panic!(
+                        "Invalid AUTH_SERVICE_TRANSPORT: {}. Expected grpc or http",
+                        transport
+                    )
+                })
+            })
+            .unwrap_or(AuthTransport::Grpc);
+
+        Self {
+            host,
+            transport,
+            grpc_port: parse_env("AUTH_SERVICE_GRPC_PORT", DEFAULT_GRPC_PORT),
+            http_port: parse_env("AUTH_SERVICE_HTTP_PORT", DEFAULT_HTTP_PORT),
+            timeout: Duration::from_millis(parse_env(
+                "AUTH_SERVICE_TIMEOUT_MS",
+                DEFAULT_TIMEOUT_MILLISECONDS,
+            )),
+            max_retries: parse_env("AUTH_SERVICE_MAX_RETRIES", DEFAULT_MAX_RETRIES),
+        }
+    }
+}
+
+pub(crate) fn parse_env<T: FromStr>(name: &str, default: T) -> T {
+    match env::var(name) {
+        Ok(value) => value
+            .parse()
+            .unwrap_or_else(|_| panic!("{} has an invalid value: {}", name, value)),
+        Err(_) => default,
+    }
+}
+
+// Verifies tokens against the auth service's `/verify-token`, over gRPC or HTTP. Connections
+// are created once and reused by every request, so clones are cheap and share them.
+#[derive(Clone)]
+pub struct RemoteVerifier {
+    grpc_client: Option<GrpcAuthClient<Channel>>,
+    http_client: reqwest::Client,
+    http_url: String,
+    timeout: Duration,
+    max_retries: u32,
+}
+
+impl RemoteVerifier {
+    pub fn new(settings: RemoteSettings) -> Result<Self, Box<dyn std::error::Error>> {
+        let grpc_client = match settings.transport {
+            AuthTransport::Grpc => {
+                let url = format!("http://{}:{}", settings.host, settings.grpc_port);
+                // Connects on first use and reconnects on its own if the auth service restarts
+                let channel = Endpoint::from_shared(url)?
+                    .connect_timeout(settings.timeout)
+                    .timeout(settings.timeout)
+                    .tcp_keepalive(Some(Duration::from_secs(60)))
+                    .connect_lazy();
+
+                Some(GrpcAuthClient::new(channel))
+            }
+            AuthTransport::Http => None,
+        };
+
+        let http_client = reqwest::Client::builder()
+            .connect_timeout(settings.timeout)
+            .timeout(settings.timeout)
+            .build()?;
+
+        Ok(Self {
+            grpc_client,
+            http_client,
+            http_url: format!(
+                "http://{}:{}/verify-token",
+                settings.host, settings.http_port
+            ),
+            timeout: settings.timeout,
+            max_retries: settings.max_retries,
+        })
+    }
+
+    // Returns whether the token is valid. Errors mean the auth service couldn't tell.
+    #[tracing::instrument(name = "Verify token with auth service", skip_all)]
+    pub async fn verify_token(&self, token: &str) -> Result<bool, AuthError> {
+        if let Some(grpc_client) = &self.grpc_client {
+            match self.verify_with_grpc(grpc_client.clone(), token).await {
+                Ok(is_valid) => return Ok(is_valid),
+                Err(status) => {
+                    tracing::warn!(
+                        "gRPC token verification failed, falling back to HTTP: {}",
+                        status
+                    );
+                }
+            }
+        }
+
+        self.verify_with_http(token).await
+    }
+
+    async fn verify_with_grpc(
+        &self,
+        mut client: GrpcAuthClient<Channel>,
+        token: &str,
+    ) -> Result<bool, tonic::Status> {
+        let mut attempt = 0;
+
+        loop {
+            let mut request = tonic::Request::new(VerifyTokenRequest {
+                token: token.to_owned(),
+            });
+            request.set_timeout(self.timeout);
+            trace_context::inject_into_metadata(request.metadata_mut());
+
+            match client.verify_token(request).await {
+                Ok(response) => return Ok(response.into_inner().status() == StatusCode::Ok),
+                Err(status)
+                    if matches!(status.code(), Code::Unauthenticated | Code::InvalidArgument) =>
+                {
+                    return Ok(false)
+                }
+                Err(status) if is_transient(status.code()) && attempt < self.max_retries => {
+                    attempt += 1;
+                    tokio::time::sleep(retry_backoff(attempt)).await;
+                }
+                Err(status) => return Err(status),
+            }
+        }
+    }
+
+    async fn verify_with_http(&self, token: &str) -> Result<bool, AuthError> {
+        let body = serde_json::json!({ "token": token });
+        let mut attempt = 0;
+
+        loop {
+            let mut headers = reqwest::header::HeaderMap::new();
+            trace_context::inject_into_headers(&mut headers);
+            let result = self
+                .http_client
+                .post(&self.http_url)
+                .headers(headers)
+                .json(&body)
+                .send()
+                .await;
+
+            let retryable = match &result {
+                Ok(response) => response.status().is_server_error(),
+                Err(e) => e.is_connect() || e.is_timeout(),
+            };
+            if retryable && attempt < self.max_retries {
+                attempt += 1;
+                tokio::time::sleep(retry_backoff(attempt)).await;
+                continue;
+            }
+
+            let response = result.map_err(|e| AuthError::Unavailable(e.to_string()))?;
+
+            return match response.status() {
+                reqwest::StatusCode::OK => Ok(true),
+                reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::BAD_REQUEST => Ok(false),
+                status => Err(AuthError::Unavailable(format!(
+                    "Unexpected status from auth service: {}",
+                    status
+                ))),
+            };
+        }
+    }
+}
+
+fn is_transient(code: Code) -> bool {
+    matches!(
+        code,
+        Code::Unavailable | Code::DeadlineExceeded | Code::ResourceExhausted | Code::Aborted
+    )
+}
+
+fn retry_backoff(attempt: u32) -> Duration {
+    Duration::from_millis(RETRY_BACKOFF_MILLISECONDS * 2_u64.pow(attempt - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::StatusCode as HttpStatusCode, routing::post, Json, Router};
+
+    // Auth service stand-in exposing only the HTTP API
+    async fn spawn_http_auth_service() -> u16 {
+        let router = Router::new().route(
+            "/verify-token",
+            post(|Json(body): Json<serde_json::Value>| async move {
+                match body["token"].as_str() {
+                    Some("valid") => HttpStatusCode::OK,
+                    _ => HttpStatusCode::UNAUTHORIZED,
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        port
+    }
+
+    async fn unused_port() -> u16 {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap().port()
+    }
+
+    fn settings(transport: AuthTransport, grpc_port: u16, http_port: u16) -> RemoteSettings {
+        RemoteSettings {
+            host: "127.0.0.1".to_owned(),
+            transport,
+            grpc_port,
+            http_port,
+            timeout: Duration::from_millis(200),
+            max_retries: 1,
+        }
+    }
+
+    #[test]
+    fn parses_transport() {
+        assert_eq!(
+            "gRPC".parse::<AuthTransport>().unwrap(),
+            AuthTransport::Grpc
+        );
+        assert_eq!(
+            "http".parse::<AuthTransport>().unwrap(),
+            AuthTransport::Http
+        );
+        assert!("tcp".parse::<AuthTransport>().is_err());
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_http_when_grpc_is_unavailable() {
+        let http_port = spawn_http_auth_service().await;
+        let verifier = RemoteVerifier::new(settings(
+            AuthTransport::Grpc,
+            unused_port().await,
+            http_port,
+        ))
+        .unwrap();
+
+        assert!(verifier.verify_token("valid").await.unwrap());
+        assert!(!verifier.verify_token("invalid").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn fails_when_auth_service_is_unreachable() {
+        let verifier = RemoteVerifier::new(settings(
+            AuthTransport::Http,
+            unused_port().await,
+            unused_port().await,
+        ))
+        .unwrap();
+
+        assert!(verifier.verify_token("valid").await.is_err());
+    }
+}
@@ -0,0 +1,146 @@
+use axum::http::{header::AUTHORIZATION, request::Parts, HeaderMap};
+use axum_extra::extract::CookieJar;
+use std::str::FromStr;
+
+pub const DEFAULT_COOKIE_NAME: &str = "jwt";
+
+// Where the token is read from in incoming requests
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenSource {
+    // Cookie with the given name, as set by the auth service on login
+    Cookie(String),
+    // `Authorization: Bearer <token>` header
+    Bearer,
+    // Bearer header first, then the cookie with the given name
+    Either(String),
+}
+
+impl Default for TokenSource {
+    fn default() -> Self {
+        Self::Cookie(DEFAULT_COOKIE_NAME.to_owned())
+    }
+}
+
+impl TokenSource {
+    pub fn extract(&self, parts: &Parts) -> Option<String> {
+        let headers = &parts.headers;
+
+        match self {
+            TokenSource::Cookie(name) => from_cookie(headers, name),
+            TokenSource::Bearer => from_bearer(headers),
+            TokenSource::Either(name) => {
+                from_bearer(headers).or_else(|| from_cookie(headers, name))
+            }
+        }
+    }
+}
+
+fn from_cookie(headers: &HeaderMap, name: &str) -> Option<String> {
+    CookieJar::from_headers(headers)
+        .get(name)
+        .map(|cookie| cookie.value().to_owned())
+        .filter(|token| !token.is_empty())
+}
+
+fn from_bearer(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get(AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return None;
+    }
+
+    let token = token.trim();
+    (!token.is_empty()).then(|| token.to_owned())
+}
+
+impl FromStr for TokenSource {
+    type Err = String;
+
+    // Accepts `cookie`, `bearer` or `either`, with an optional cookie name: `cookie:session`
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (kind, cookie_name) = match value.split_once(':') {
+            Some((kind, name)) => (kind, name.to_owned()),
+            None => (value, DEFAULT_COOKIE_NAME.to_owned()),
+        };
+
+        match kind.to_ascii_lowercase().as_str() {
+            "cookie" => Ok(Self::Cookie(cookie_name)),
+            "bearer" => Ok(Self::Bearer),
+            "either" => Ok(Self::Either(cookie_name)),
+            other => Err(format!(
+                "Invalid token source: {}. Expected cookie, bearer or either",
+                other
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::{header::COOKIE, Request};
+
+    fn parts(headers: &[(&str, &str)]) -> Parts {
+        let mut request = Request::builder();
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        request.body(()).unwrap().into_parts().0
+    }
+
+    #[test]
+    fn reads_token_from_cookie() {
+        let parts = parts(&[(COOKIE.as_str(), "theme=dark; jwt=abc")]);
+
+        assert_eq!(
+            TokenSource::default().extract(&parts),
+            Some("abc".to_owned())
+        );
+        assert_eq!(TokenSource::Bearer.extract(&parts), None);
+    }
+
+    #[test]
+    fn reads_token_from_bearer_header() {
+        let parts = parts(&[(AUTHORIZATION.as_str(), "bearer abc")]);
+
+        assert_eq!(TokenSource::Bearer.extract(&parts), Some("abc".to_owned()));
+        assert_eq!(TokenSource::default().extract(&parts), None);
+        assert_eq!(
+            TokenSource::Bearer.extract(&self::parts(&[(AUTHORIZATION.as_str(), "Basic abc")])),
+            None
+        );
+    }
+
+    #[test]
+    fn either_prefers_bearer_header() {
+        let source = TokenSource::Either("jwt".to_owned());
+        let both = parts(&[
+            (AUTHORIZATION.as_str(), "Bearer from-header"),
+            (COOKIE.as_str(), "jwt=from-cookie"),
+        ]);
+
+        assert_eq!(source.extract(&both), Some("from-header".to_owned()));
+        assert_eq!(
+            source.extract(&parts(&[(COOKIE.as_str(), "jwt=from-cookie")])),
+            Some("from-cookie".to_owned())
+        );
+    }
+
+    #[test]
+    fn parses_token_source() {
+        assert_eq!(
+            "cookie".parse::<TokenSource>().unwrap(),
+            TokenSource::Cookie("jwt".to_owned())
+        );
+        assert_eq!(
+            "Either:session".parse::<TokenSource>().unwrap(),
+            TokenSource::Either("session".to_owned())
+        );
+        assert_eq!(
+            "bearer".parse::<TokenSource>().unwrap(),
+            TokenSource::Bearer
+        );
+        assert!("header".parse::<TokenSource>().is_err());
+    }
+}
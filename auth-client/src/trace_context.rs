@@ -0,0 +1,80 @@
+use opentelemetry::{global, propagation::Injector};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use tonic::metadata::{MetadataKey, MetadataMap, MetadataValue};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+// Writes the W3C trace context of the current span, `traceparent` and `tracestate`, into
+// outgoing verify calls so the auth service continues the caller's trace. Nothing is written
+// until the service installs a propagator and an OpenTelemetry tracing layer.
+pub(crate) fn inject_into_headers(headers: &mut HeaderMap) {
+    inject(&mut HeadersInjector(headers));
+}
+
+pub(crate) fn inject_into_metadata(metadata: &mut MetadataMap) {
+    inject(&mut MetadataInjector(metadata));
+}
+
+fn inject(injector: &mut dyn Injector) {
+    let context = tracing::Span::current().context();
+    global::get_text_map_propagator(|propagator| propagator.inject_context(&context, injector));
+}
+
+struct HeadersInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeadersInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(key.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+struct MetadataInjector<'a>(&'a mut MetadataMap);
+
+impl Injector for MetadataInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(key), Ok(value)) = (
+            MetadataKey::from_bytes(key.as_bytes()),
+            MetadataValue::try_from(value),
+        ) {
+            self.0.insert(key, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::{TraceContextExt, TracerProvider as _};
+    use opentelemetry_sdk::{propagation::TraceContextPropagator, trace::TracerProvider};
+    use tracing_subscriber::prelude::*;
+
+    #[test]
+    fn injects_the_trace_of_the_current_span() {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        // Spans are only recorded while the provider is alive
+        let provider = TracerProvider::builder().build();
+        let tracer = provider.tracer("test");
+        let subscriber =
+            tracing_subscriber::registry().with(tracing_opentelemetry::layer().with_tracer(tracer));
+
+        let (headers, metadata, trace_id) = tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("verify");
+            let _guard = span.enter();
+            let mut headers = HeaderMap::new();
+            let mut metadata = MetadataMap::new();
+            inject_into_headers(&mut headers);
+            inject_into_metadata(&mut metadata);
+
+            let trace_id = span.context().span().span_context().trace_id();
+            (headers, metadata, trace_id.to_string())
+        });
+
+        let traceparent = headers["traceparent"].to_str().unwrap();
+        assert!(traceparent.starts_with(&format!("00-{}-", trace_id)));
+        assert_eq!(metadata.get("traceparent").unwrap(), traceparent);
+    }
+}
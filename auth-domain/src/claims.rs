@@ -0,0 +1,10 @@
+use serde::{Deserialize, Serialize};
+
+// Claims of the JWTs issued by the auth service
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Claims {
+    // Email of the authenticated user
+    pub sub: String,
+    // Expiration as a unix timestamp in seconds
+    pub exp: usize,
+}
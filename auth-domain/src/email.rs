@@ -11,7 +11,8 @@ impl Email {
         if email.expose_secret().validate_email() {
             Ok(Self(email))
         } else {
-            Err(eyre!("Invalid email: {}", email.expose_secret()))
+            // The address itself stays out of the message, it would end up in the logs
+            Err(eyre!("Invalid email"))
         }
     }
 }
@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+// Errors the auth service reports to its clients, over HTTP in the `code` of error
+// responses and over gRPC as the status code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    UserAlreadyExists,
+    InvalidCredentials,
+    IncorrectCredentials,
+    MissingToken,
+    InvalidRecaptcha,
+    InvalidToken,
+    InvalidCsrfToken,
+    UnexpectedError,
+}
+
+impl ErrorCode {
+    // Message safe to show to the client
+    pub fn message(&self) -> &'static str {
+        match self {
+            ErrorCode::UserAlreadyExists => "User already exists",
+            ErrorCode::InvalidCredentials => "Invalid credentials",
+            ErrorCode::IncorrectCredentials => "Unauthorized",
+            ErrorCode::MissingToken => "Missing auth token",
+            ErrorCode::InvalidRecaptcha => "Invalid captcha",
+            ErrorCode::InvalidToken => "Invalid auth token",
+            ErrorCode::InvalidCsrfToken => "Missing or invalid CSRF token",
+            ErrorCode::UnexpectedError => "Unexpected error",
+        }
+    }
+
+    // Same as the serialized code, e.g. for metric labels
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::UserAlreadyExists => "user_already_exists",
+            ErrorCode::InvalidCredentials => "invalid_credentials",
+            ErrorCode::IncorrectCredentials => "incorrect_credentials",
+            ErrorCode::MissingToken => "missing_token",
+            ErrorCode::InvalidRecaptcha => "invalid_recaptcha",
+            ErrorCode::InvalidToken => "invalid_token",
+            ErrorCode::InvalidCsrfToken => "invalid_csrf_token",
+            ErrorCode::UnexpectedError => "unexpected_error",
+        }
+    }
+}
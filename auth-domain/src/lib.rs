@@ -0,0 +1,10 @@
+// Types shared by the auth service and its clients
+
+mod claims;
+mod email;
+mod error_code;
+
+// Re-exporting
+pub use claims::Claims;
+pub use email::Email;
+pub use error_code::ErrorCode;
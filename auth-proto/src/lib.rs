@@ -0,0 +1,20 @@
+// gRPC API of the auth service, generated from proto/authentication.proto. Both the server
+// and its clients depend on this crate so they can't drift apart.
+
+use auth_domain::ErrorCode;
+use tonic::Code;
+
+tonic::include_proto!("authentication");
+
+// gRPC status code the server answers with for each error
+pub fn grpc_code(code: ErrorCode) -> Code {
+    match code {
+        ErrorCode::UserAlreadyExists => Code::AlreadyExists,
+        ErrorCode::InvalidCredentials | ErrorCode::InvalidRecaptcha => Code::InvalidArgument,
+        ErrorCode::IncorrectCredentials | ErrorCode::MissingToken | ErrorCode::InvalidToken => {
+            Code::Unauthenticated
+        }
+        ErrorCode::InvalidCsrfToken => Code::PermissionDenied,
+        ErrorCode::UnexpectedError => Code::Internal,
+    }
+}
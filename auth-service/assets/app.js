@@ -1,4 +1,12 @@
 const recaptcha_client_id = "6LfMkucpAAAAAFvpGkWuxSxc3ohij7YIclleLh4D";
+
+// Cookie-authenticated POSTs must echo the CSRF token in a header
+function withCsrfToken(headers) {
+    return fetch(`${window.location.origin}/auth/csrf`, { credentials: 'include' })
+        .then(response => response.json())
+        .then(data => ({ ...headers, 'X-CSRF-Token': data.csrfToken }));
+}
+
 const loginSection = document.getElementById("login-section");
 const twoFASection = document.getElementById("2fa-section");
 const signupSection = document.getElementById("signup-section");
@@ -45,13 +53,11 @@ loginButton.addEventListener("click", (e) => {
             const email = loginForm.email.value;
             const password = loginForm.password.value;
         
-            fetch(`${window.location.origin}/auth/login`, {
+            withCsrfToken({ 'Content-Type': 'application/json' }).then(headers => fetch(`${window.location.origin}/auth/login`, {
                 method: 'POST',
-                headers: {
-                    'Content-Type': 'application/json',
-                },
+                headers,
                 body: JSON.stringify({ email, password, recaptcha: token }),
-            }).then(response => {
+            })).then(response => {
                 if (response.status === 206) {
                     TwoFAForm.email.value = email;
                     response.json().then(data => {
@@ -99,13 +105,11 @@ signupButton.addEventListener("click", (e) => {
             const password = signupForm.password.value;
             const requires2FA = signupForm.twoFA.checked;
         
-            fetch(`${window.location.origin}/auth/signup`, {
+            withCsrfToken({ 'Content-Type': 'application/json' }).then(headers => fetch(`${window.location.origin}/auth/signup`, {
                 method: 'POST',
-                headers: {
-                    'Content-Type': 'application/json',
-                },
+                headers,
                 body: JSON.stringify({ email, password, requires2FA, recaptcha: token }),
-            }).then(response => {
+            })).then(response => {
                 if (response.ok) {
                     signupForm.email.value = "";
                     signupForm.password.value = "";
@@ -142,13 +146,11 @@ TwoFAButton.addEventListener("click", (e) => {
     const loginAttemptId = TwoFAForm.login_attempt_id.value;
     const TwoFACode = TwoFAForm.email_code.value;
 
-    fetch(`${window.location.origin}/auth/verify-2fa`, {
+    withCsrfToken({ 'Content-Type': 'application/json' }).then(headers => fetch(`${window.location.origin}/auth/verify-2fa`, {
         method: 'POST',
-        headers: {
-            'Content-Type': 'application/json',
-        },
+        headers,
         body: JSON.stringify({ email, loginAttemptId, "2FACode": TwoFACode }),
-    }).then(response => {
+    })).then(response => {
         if (response.ok) {
             TwoFAForm.email.value = "";
             TwoFAForm.email_code.value = "";
@@ -0,0 +1,10 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS two_fa_codes(
+    email TEXT NOT NULL PRIMARY KEY,
+    login_attempt_id_hash TEXT NOT NULL,
+    code_hash TEXT NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS two_fa_codes_expires_at_idx
+    ON two_fa_codes (expires_at);
@@ -1,15 +1,21 @@
-use crate::domain::{
-    data_stores::{BannedTokenStore, TwoFACodeStore, UserStore},
-    EmailClient,
+use crate::{
+    domain::{
+        data_stores::{BannedTokenStore, EmailOutboxStore, TwoFACodeStore, UserStore},
+        EmailClient, HealthCheck,
+    },
+    settings::Settings,
+    utils::metrics::Metrics,
 };
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
 
 // Using a type alias to improve readability!
-pub type UserStoreType = Arc<RwLock<dyn UserStore + Send + Sync>>;
-pub type BannedTokenStoreType = Arc<RwLock<dyn BannedTokenStore + Send + Sync>>;
-pub type TwoFACodeStoreType = Arc<RwLock<dyn TwoFACodeStore + Send + Sync>>;
+pub type UserStoreType = Arc<dyn UserStore + Send + Sync>;
+pub type BannedTokenStoreType = Arc<dyn BannedTokenStore + Send + Sync>;
+pub type TwoFACodeStoreType = Arc<dyn TwoFACodeStore + Send + Sync>;
 pub type EmailClientType = Arc<dyn EmailClient + Send + Sync>;
+pub type EmailOutboxStoreType = Arc<dyn EmailOutboxStore + Send + Sync>;
+pub type HealthChecksType = Arc<Vec<Arc<dyn HealthCheck + Send + Sync>>>;
 
 #[derive(Clone)]
 pub struct AppState {
@@ -17,20 +23,37 @@ pub struct AppState {
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
     pub email_client: EmailClientType,
+    pub email_outbox: EmailOutboxStoreType,
+    // Dependencies checked by the readiness probe
+    pub health_checks: HealthChecksType,
+    pub metrics: Arc<Metrics>,
+    pub settings: Arc<Settings>,
+    // Cancelled as soon as the service starts shutting down, the readiness probe fails from then on
+    pub shutdown: CancellationToken,
 }
 
 impl AppState {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         user_store: UserStoreType,
         banned_token_store: BannedTokenStoreType,
         two_fa_code_store: TwoFACodeStoreType,
         email_client: EmailClientType,
+        email_outbox: EmailOutboxStoreType,
+        health_checks: HealthChecksType,
+        metrics: Arc<Metrics>,
+        settings: Arc<Settings>,
     ) -> Self {
         Self {
             user_store,
             banned_token_store,
             two_fa_code_store,
             email_client,
+            email_outbox,
+            health_checks,
+            metrics,
+            settings,
+            shutdown: CancellationToken::new(),
         }
     }
 }
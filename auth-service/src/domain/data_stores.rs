@@ -1,10 +1,12 @@
-use crate::domain::{Email, Password, User};
+use crate::domain::{Email, OutboxEmail, OutboxEntry, OutboxStatus, Password, User};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use color_eyre::eyre::{eyre, Context, Report, Result};
 use rand::Rng;
 use regex_automata::meta::Regex;
 use secrecy::{ExposeSecret, Secret};
 use thiserror::Error;
+use uuid::Uuid;
 
 #[derive(Debug, Error)]
 pub enum UserStoreError {
@@ -32,8 +34,8 @@ impl PartialEq for UserStoreError {
 
 #[async_trait]
 pub trait UserStore {
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError>;
-    async fn delete_user(&mut self, user: User) -> Result<(), UserStoreError>;
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError>;
+    async fn delete_user(&self, user: User) -> Result<(), UserStoreError>;
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError>;
     async fn validate_user(&self, email: &Email, password: &Password)
         -> Result<(), UserStoreError>;
@@ -43,38 +45,66 @@ pub trait UserStore {
 pub enum BannedTokenStoreError {
     #[error("Invalid token")]
     InvalidToken,
+    #[error("Token already expired")]
+    TokenExpired,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
 
 #[async_trait]
 pub trait BannedTokenStore {
-    async fn add_token(&mut self, token: Secret<String>) -> Result<(), BannedTokenStoreError>;
+    // Keeps the token until `exp`, its expiration as a unix timestamp in seconds, after which
+    // it's rejected anyway. Tokens that have already expired aren't stored.
+    async fn add_token(
+        &self,
+        token: Secret<String>,
+        exp: usize,
+    ) -> Result<(), BannedTokenStoreError>;
     async fn contains_token(&self, token: Secret<String>) -> Result<bool, BannedTokenStoreError>;
-    async fn empty_store(&mut self) -> Result<(), BannedTokenStoreError>;
+    async fn empty_store(&self) -> Result<(), BannedTokenStoreError>;
+
+    // Deletes the expired tokens, returning how many. Only needed by stores that don't expire
+    // them on their own, see `ExpiryPurgeWorker`.
+    async fn purge_expired(&self) -> Result<u64, BannedTokenStoreError> {
+        Ok(0)
+    }
 }
 
 #[async_trait]
 pub trait TwoFACodeStore {
     async fn add_code(
-        &mut self,
+        &self,
         email: Email,
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError>;
 
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError>;
+    async fn remove_code(&self, email: &Email) -> Result<(), TwoFACodeStoreError>;
 
-    async fn get_code(
+    // Checks the login attempt and code of `email` in constant time. Nothing is handed out, so
+    // a store may keep only hashes of them.
+    async fn verify_code(
         &self,
         email: &Email,
-    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError>;
+        login_attempt_id: &LoginAttemptId,
+        code: &TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError>;
+
+    // Deletes the expired codes, returning how many, like `BannedTokenStore::purge_expired`
+    async fn purge_expired(&self) -> Result<u64, TwoFACodeStoreError> {
+        Ok(0)
+    }
 }
 
+// How long a login attempt can be completed with its 2FA code
+pub const TWO_FA_CODE_TTL_SECONDS: i64 = 10 * 60;
+
 #[derive(Debug, Error)]
 pub enum TwoFACodeStoreError {
     #[error("Login Attempt ID not found")]
     LoginAttemptIdNotFound,
+    #[error("Incorrect login attempt ID or 2FA code")]
+    IncorrectCode,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
@@ -84,11 +114,53 @@ impl PartialEq for TwoFACodeStoreError {
         matches!(
             (self, other),
             (Self::LoginAttemptIdNotFound, Self::LoginAttemptIdNotFound)
+                | (Self::IncorrectCode, Self::IncorrectCode)
                 | (Self::UnexpectedError(_), Self::UnexpectedError(_))
         )
     }
 }
 
+#[async_trait]
+pub trait EmailOutboxStore {
+    // Returns `None` when an email with the same idempotency key is already queued.
+    async fn enqueue(
+        &self,
+        email: OutboxEmail,
+        available_at: DateTime<Utc>,
+    ) -> Result<Option<Uuid>, EmailOutboxStoreError>;
+
+    // Returns up to `limit` pending emails that are due and hides them from other
+    // workers until `lease_until`, so a crashed worker's emails are picked up again.
+    async fn claim_due(
+        &self,
+        limit: i64,
+        lease_until: DateTime<Utc>,
+    ) -> Result<Vec<OutboxEntry>, EmailOutboxStoreError>;
+
+    async fn mark_sent(&self, id: Uuid) -> Result<(), EmailOutboxStoreError>;
+
+    // Records a failed attempt. Passing `None` as `retry_at` moves the email to the dead letters.
+    async fn mark_failed(
+        &self,
+        id: Uuid,
+        error: String,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), EmailOutboxStoreError>;
+
+    async fn get_status(
+        &self,
+        idempotency_key: &str,
+    ) -> Result<Option<OutboxStatus>, EmailOutboxStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum EmailOutboxStoreError {
+    #[error("Outbox email not found")]
+    EmailNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
 #[derive(Debug, Clone)]
 pub struct LoginAttemptId(Secret<String>);
 
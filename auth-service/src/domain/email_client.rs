@@ -1,7 +1,21 @@
 use super::Email;
 use color_eyre::eyre::Result;
 
+// A fully rendered email, ready to be handed to any `EmailClient` implementation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmailMessage {
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: String,
+}
+
 #[async_trait::async_trait]
 pub trait EmailClient {
-    async fn send_email(&self, recipient: &Email, subject: &str, content: &str) -> Result<()>;
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()>;
+
+    // Whether the provider can be reached, for the readiness probe. Clients that don't talk
+    // to a provider are always ready.
+    async fn check_connection(&self) -> Result<()> {
+        Ok(())
+    }
 }
@@ -0,0 +1,76 @@
+use super::{Email, EmailMessage};
+use color_eyre::eyre::{eyre, Report, Result};
+use std::str::FromStr;
+use uuid::Uuid;
+
+// An email waiting in the outbox. The idempotency key identifies the logical email
+// (e.g. the 2FA code of a given login attempt), so queueing it twice is a no-op.
+#[derive(Debug, Clone)]
+pub struct OutboxEmail {
+    pub idempotency_key: String,
+    pub recipient: Email,
+    pub message: EmailMessage,
+}
+
+#[derive(Debug, Clone)]
+pub struct OutboxEntry {
+    pub id: Uuid,
+    // Number of failed delivery attempts so far
+    pub attempts: i32,
+    pub email: OutboxEmail,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutboxStatus {
+    Pending,
+    Sent,
+    // Gave up after too many failed attempts
+    Dead,
+}
+
+impl OutboxStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Sent => "sent",
+            Self::Dead => "dead",
+        }
+    }
+}
+
+impl FromStr for OutboxStatus {
+    type Err = Report;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "pending" => Ok(Self::Pending),
+            "sent" => Ok(Self::Sent),
+            "dead" => Ok(Self::Dead),
+            other => Err(eyre!("Unknown outbox status: {}", other)),
+        }
+    }
+}
+
+// How a request handler hands an email over for delivery.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmailDelivery {
+    // Only write to the outbox and let the background worker send it
+    Outbox,
+    // Write to the outbox and try to send right away, leaving retries to the worker on failure
+    SyncWithFallback,
+}
+
+impl FromStr for EmailDelivery {
+    type Err = Report;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "outbox" => Ok(Self::Outbox),
+            "sync_with_fallback" => Ok(Self::SyncWithFallback),
+            other => Err(eyre!(
+                "Invalid email delivery mode: {}. Expected outbox or sync_with_fallback",
+                other
+            )),
+        }
+    }
+}
@@ -1,23 +1,39 @@
-use std::env;
+use color_eyre::eyre::{eyre, Report, Result};
+use std::str::FromStr;
 
-pub fn is_local() -> bool {
-    let environment = env::var("ENVIRONMENT").expect("Missing ENVIRONMENT environment variable.");
-
-    environment == *"local"
+// Where the service runs, picks the defaults that only make sense in one of them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Environment {
+    // Developer machine or CI, plain HTTP on localhost
+    Local,
+    // Deployed behind the HTTPS reverse proxy
+    Remote,
 }
 
-pub fn is_remote() -> bool {
-    let environment = env::var("ENVIRONMENT").expect("Missing ENVIRONMENT environment variable.");
+impl Environment {
+    pub fn is_local(&self) -> bool {
+        *self == Self::Local
+    }
 
-    environment == *"remote"
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Local => "local",
+            Self::Remote => "remote",
+        }
+    }
 }
 
-pub fn get_env<Env>(env_name: Env) -> String
-where
-    Env: Into<String>,
-{
-    let env_name = env_name.into();
+impl FromStr for Environment {
+    type Err = Report;
 
-    env::var(&env_name)
-        .unwrap_or_else(|_| panic!("{}", format!("Missing {} environment variable.", &env_name)))
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "local" => Ok(Self::Local),
+            "remote" => Ok(Self::Remote),
+            other => Err(eyre!(
+                "Invalid environment: {}. Expected local or remote",
+                other
+            )),
+        }
+    }
 }
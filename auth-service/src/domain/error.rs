@@ -1,3 +1,4 @@
+use auth_domain::ErrorCode;
 use color_eyre::eyre::Report;
 use thiserror::Error;
 
@@ -15,6 +16,23 @@ pub enum AuthAPIError {
     InvalidRecaptcha,
     #[error("Invalid token")]
     InvalidToken,
+    #[error("Invalid CSRF token")]
+    InvalidCsrfToken,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
+
+impl AuthAPIError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            AuthAPIError::UserAlreadyExists => ErrorCode::UserAlreadyExists,
+            AuthAPIError::InvalidCredentials => ErrorCode::InvalidCredentials,
+            AuthAPIError::IncorrectCredentials => ErrorCode::IncorrectCredentials,
+            AuthAPIError::MissingToken => ErrorCode::MissingToken,
+            AuthAPIError::InvalidRecaptcha => ErrorCode::InvalidRecaptcha,
+            AuthAPIError::InvalidToken => ErrorCode::InvalidToken,
+            AuthAPIError::InvalidCsrfToken => ErrorCode::InvalidCsrfToken,
+            AuthAPIError::UnexpectedError(_) => ErrorCode::UnexpectedError,
+        }
+    }
+}
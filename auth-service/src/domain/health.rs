@@ -0,0 +1,140 @@
+use color_eyre::eyre::Result;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::task::JoinSet;
+
+// A dependency the service can't serve requests without, e.g. the database
+#[async_trait::async_trait]
+pub trait HealthCheck {
+    // Identifies the dependency in the readiness report
+    fn name(&self) -> &'static str;
+
+    async fn check(&self) -> Result<()>;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DependencyHealth {
+    pub name: &'static str,
+    pub healthy: bool,
+    pub latency: Duration,
+}
+
+// Runs every check concurrently, a check that takes longer than `timeout` counts as failed.
+// The reasons are only logged, the report ends up in a public response.
+pub async fn check_dependencies(
+    checks: &[Arc<dyn HealthCheck + Send + Sync>],
+    timeout: Duration,
+) -> Vec<DependencyHealth> {
+    let mut tasks = JoinSet::new();
+
+    for (index, check) in checks.iter().enumerate() {
+        let check = check.clone();
+        tasks.spawn(async move {
+            let started = Instant::now();
+            let healthy = match tokio::time::timeout(timeout, check.check()).await {
+                Ok(Ok(())) => true,
+                Ok(Err(e)) => {
+                    tracing::warn!("{} health check failed: {:?}", check.name(), e);
+                    false
+                }
+                Err(_) => {
+                    tracing::warn!(
+                        "{} health check timed out after {:?}",
+                        check.name(),
+                        timeout
+                    );
+                    false
+                }
+            };
+
+            let health = DependencyHealth {
+                name: check.name(),
+                healthy,
+                latency: started.elapsed(),
+            };
+            (index, health)
+        });
+    }
+
+    let mut results = Vec::with_capacity(checks.len());
+    while let Some(result) = tasks.join_next().await {
+        match result {
+            Ok(result) => results.push(result),
+            Err(e) => tracing::error!("Health check panicked: {:?}", e),
+        }
+    }
+
+    // A check that panicked is reported as failed rather than left out
+    let mut report: Vec<DependencyHealth> = checks
+        .iter()
+        .map(|check| DependencyHealth {
+            name: check.name(),
+            healthy: false,
+            latency: Duration::ZERO,
+        })
+        .collect();
+    for (index, health) in results {
+        report[index] = health;
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use color_eyre::eyre::eyre;
+
+    struct FakeCheck {
+        name: &'static str,
+        delay: Duration,
+        healthy: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl HealthCheck for FakeCheck {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        async fn check(&self) -> Result<()> {
+            tokio::time::sleep(self.delay).await;
+            match self.healthy {
+                true => Ok(()),
+                false => Err(eyre!("Connection refused")),
+            }
+        }
+    }
+
+    fn fake(
+        name: &'static str,
+        delay_ms: u64,
+        healthy: bool,
+    ) -> Arc<dyn HealthCheck + Send + Sync> {
+        Arc::new(FakeCheck {
+            name,
+            delay: Duration::from_millis(delay_ms),
+            healthy,
+        })
+    }
+
+    #[tokio::test]
+    async fn reports_every_dependency_in_order() {
+        let checks = [
+            fake("postgres", 0, true),
+            fake("redis", 0, false),
+            fake("email", 1000, true),
+        ];
+
+        let started = Instant::now();
+        let report = check_dependencies(&checks, Duration::from_millis(50)).await;
+
+        let names: Vec<_> = report.iter().map(|health| health.name).collect();
+        let healthy: Vec<_> = report.iter().map(|health| health.healthy).collect();
+        assert_eq!(names, ["postgres", "redis", "email"]);
+        assert_eq!(healthy, [true, false, false]);
+        assert!(started.elapsed() < Duration::from_millis(500));
+    }
+}
@@ -0,0 +1,111 @@
+use std::str::FromStr;
+
+/// Languages we have email copy for. Anything else falls back to `Locale::En`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    #[default]
+    En,
+    Es,
+}
+
+impl Locale {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::En => "en",
+            Self::Es => "es",
+        }
+    }
+
+    /// Picks the locale for a message. An explicit user preference wins over the
+    /// `Accept-Language` hint; when neither matches a supported locale we use the default.
+    pub fn negotiate(preference: Option<&str>, accept_language: Option<&str>) -> Self {
+        preference
+            .and_then(|value| value.parse().ok())
+            .or_else(|| accept_language.and_then(Self::from_accept_language))
+            .unwrap_or_default()
+    }
+
+    /// Parses an `Accept-Language` header value, e.g. `es-ES,es;q=0.9,en;q=0.8`,
+    /// and returns the supported locale with the highest quality value.
+    pub fn from_accept_language(header: &str) -> Option<Self> {
+        let mut candidates = header
+            .split(',')
+            .filter_map(|entry| {
+                let mut parts = entry.trim().split(';');
+                let tag = parts.next()?.trim();
+                let quality = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .map(|q| q.trim().parse::<f32>().unwrap_or(0.0))
+                    .unwrap_or(1.0);
+
+                if quality <= 0.0 {
+                    return None;
+                }
+
+                tag.parse::<Locale>().ok().map(|locale| (locale, quality))
+            })
+            .collect::<Vec<_>>();
+
+        // Stable sort keeps the header order for entries with the same quality
+        candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+        candidates.first().map(|(locale, _)| *locale)
+    }
+}
+
+impl FromStr for Locale {
+    type Err = ();
+
+    // Accepts bare language codes ("es") as well as region subtags ("es-MX", "es_MX").
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let language = value
+            .split(['-', '_'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        match language.as_str() {
+            "en" => Ok(Self::En),
+            "es" => Ok(Self::Es),
+            _ => Err(()),
+        }
+    }
+}
+
+impl std::fmt::Display for Locale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_language_codes_with_region() {
+        assert_eq!("es-MX".parse::<Locale>(), Ok(Locale::Es));
+        assert_eq!("EN_us".parse::<Locale>(), Ok(Locale::En));
+        assert_eq!("fr".parse::<Locale>(), Err(()));
+    }
+
+    #[test]
+    fn accept_language_picks_highest_quality_supported_locale() {
+        assert_eq!(
+            Locale::from_accept_language("fr-FR,es;q=0.9,en;q=0.8"),
+            Some(Locale::Es)
+        );
+        assert_eq!(
+            Locale::from_accept_language("es;q=0.2,en;q=0.7"),
+            Some(Locale::En)
+        );
+        assert_eq!(Locale::from_accept_language("fr,de;q=0.5"), None);
+        assert_eq!(Locale::from_accept_language("es;q=0"), None);
+    }
+
+    #[test]
+    fn preference_wins_over_accept_language() {
+        assert_eq!(Locale::negotiate(Some("es"), Some("en-US")), Locale::Es);
+        assert_eq!(Locale::negotiate(Some("fr"), Some("es-ES")), Locale::Es);
+        assert_eq!(Locale::negotiate(None, None), Locale::En);
+    }
+}
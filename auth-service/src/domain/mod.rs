@@ -1,15 +1,20 @@
 pub mod data_stores;
-pub mod email;
 pub mod email_client;
+pub mod email_outbox;
 pub mod environment;
 pub mod error;
+pub mod health;
+pub mod locale;
 pub mod password;
 pub mod path;
 pub mod user;
 
 pub use crate::domain::data_stores::*;
-pub use crate::domain::email::*;
 pub use crate::domain::email_client::*;
+pub use crate::domain::email_outbox::*;
 pub use crate::domain::error::*;
+pub use crate::domain::health::*;
+pub use crate::domain::locale::*;
 pub use crate::domain::password::*;
 pub use crate::domain::user::User;
+pub use auth_domain::Email;
@@ -19,7 +19,6 @@ pub struct Password(Secret<String>);
  *
  * Source: Gemini
  */
-
 impl Password {
     pub fn parse(password: Secret<String>) -> Result<Password> {
         if validate_password(&password) {
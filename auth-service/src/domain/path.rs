@@ -4,9 +4,15 @@ pub enum Paths {
     Signup,
     Login,
     Logout,
+    Session,
+    Csrf,
     Verify2FA,
     VerifyToken,
     Users,
+    DevMailbox,
+    HealthLive,
+    HealthReady,
+    Metrics,
 }
 
 impl Paths {
@@ -16,9 +22,15 @@ impl Paths {
             Self::Signup => "/signup",
             Self::Login => "/login",
             Self::Logout => "/logout",
+            Self::Session => "/session",
+            Self::Csrf => "/csrf",
             Self::Verify2FA => "/verify-2fa",
             Self::VerifyToken => "/verify-token",
             Self::Users => "/users",
+            Self::DevMailbox => "/dev/mailbox",
+            Self::HealthLive => "/health/live",
+            Self::HealthReady => "/health/ready",
+            Self::Metrics => "/metrics",
         }
     }
 }
@@ -30,9 +42,15 @@ impl std::fmt::Display for Paths {
             Self::Signup => "/signup",
             Self::Login => "/login",
             Self::Logout => "/logout",
+            Self::Session => "/session",
+            Self::Csrf => "/csrf",
             Self::Verify2FA => "/verify-2fa",
             Self::VerifyToken => "/verify-token",
             Self::Users => "/users",
+            Self::DevMailbox => "/dev/mailbox",
+            Self::HealthLive => "/health/live",
+            Self::HealthReady => "/health/ready",
+            Self::Metrics => "/metrics",
         };
         write!(f, "{}", output)
     }
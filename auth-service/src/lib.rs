@@ -1,52 +1,86 @@
 use app_state::AppState;
+use auth_domain::ErrorCode;
 use axum::{
-    http::{Method, StatusCode},
+    http::StatusCode,
+    middleware,
     response::IntoResponse,
-    routing::{delete, post},
+    routing::{delete, get, post},
     serve::Serve,
     Json, Router,
 };
-use domain::{environment::get_env, AuthAPIError};
-use redis::{Client as RedisClient, RedisResult};
+use domain::AuthAPIError;
+use redis::RedisResult;
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
+use services::{
+    data_stores::RedisConnection,
+    email_outbox::EmailOutboxWorker,
+    expiry_purge::ExpiryPurgeWorker,
+    grpc_auth::{AuthServer, GrpcAuthService},
+};
+use settings::{RedisSettings, Settings, StoreBackend};
 use sqlx::{postgres::PgPoolOptions, PgPool};
-use std::error::Error;
-use tower_http::{cors::CorsLayer, services::ServeDir, trace::TraceLayer};
-use utils::constants;
-use utils::tracing::{make_span_with_request_id, on_request, on_response};
+use std::{error::Error, future::Future, sync::Arc};
+use tokio::{net::TcpListener, time::Instant};
+use tokio_stream::wrappers::TcpListenerStream;
+use tokio_util::sync::CancellationToken;
+use tower_http::{services::ServeDir, trace::TraceLayer};
+use utils::csrf::CsrfLayer;
+use utils::metrics::track_http_metrics;
+use utils::shutdown::shutdown_signal;
+use utils::tracing::{
+    make_grpc_span, make_span_with_request_id, on_request, on_response, propagate_request_id,
+};
 
 pub mod app_state;
 pub mod domain;
 pub mod routes;
 pub mod services;
+pub mod settings;
 pub mod utils;
 
 // This struct encapsulates our application-related logic.
 pub struct Application {
     server: Serve<Router, Router>,
+    grpc_listener: TcpListener,
+    grpc_service: GrpcAuthService,
+    email_outbox_worker: EmailOutboxWorker,
+    // Only for the stores that don't expire entries on their own
+    expiry_purge_worker: Option<ExpiryPurgeWorker>,
+    settings: Arc<Settings>,
+    // Cancelled once shutting down, see `AppState::shutdown`
+    shutdown: CancellationToken,
     pub address: String,
+    pub grpc_address: String,
 }
 
 impl Application {
-    pub async fn build(app_state: AppState, address: &str) -> Result<Self, Box<dyn Error>> {
-        let droplet_ip = get_env(constants::env::DROPLET_IP_ENV_VAR);
-        let base_path = get_env(constants::env::BASE_PATH_ENV_VAR);
-        let allowed_origins = [
-            "http://localhost:8000".parse()?,
-            format!("https://{}:8000", droplet_ip.as_str()).parse()?,
-            format!("{}/app", base_path.as_str()).parse()?,
-        ];
-
-        let cors = CorsLayer::new()
-            .allow_methods([Method::GET, Method::POST])
-            .allow_credentials(true)
-            .allow_origin(allowed_origins);
-
-        let router = Router::new()
+    pub async fn build(app_state: AppState) -> Result<Self, Box<dyn Error>> {
+        let settings = app_state.settings.clone();
+
+        let email_outbox_worker = EmailOutboxWorker::new(
+            app_state.email_outbox.clone(),
+            app_state.email_client.clone(),
+            app_state.metrics.clone(),
+        );
+        let expiry_purge_worker = match settings.stores.backend {
+            // EXPIREAT drops the keys
+            StoreBackend::Redis(_) => None,
+            StoreBackend::Postgres => Some(ExpiryPurgeWorker::new(
+                app_state.banned_token_store.clone(),
+                app_state.two_fa_code_store.clone(),
+                settings.stores.purge_interval,
+            )),
+        };
+        let grpc_service = GrpcAuthService::new(app_state.clone());
+        let shutdown = app_state.shutdown.clone();
+
+        let mut router = Router::new()
             .route(domain::path::Paths::Signup.as_str(), post(routes::signup))
             .route(domain::path::Paths::Login.as_str(), post(routes::login))
             .route(domain::path::Paths::Logout.as_str(), post(routes::logout))
+            .route(domain::path::Paths::Session.as_str(), get(routes::session))
+            .route(domain::path::Paths::Csrf.as_str(), get(routes::csrf))
             .route(
                 domain::path::Paths::Verify2FA.as_str(),
                 post(routes::verify_2fa),
@@ -59,31 +93,151 @@ impl Application {
                 format!("{}/:email", domain::path::Paths::Users.as_str()).as_str(),
                 delete(routes::delete),
             )
-            .nest_service(domain::path::Paths::Root.as_str(), ServeDir::new("assets"))
+            .route(domain::path::Paths::HealthLive.as_str(), get(routes::live))
+            .route(
+                domain::path::Paths::HealthReady.as_str(),
+                get(routes::ready),
+            )
+            .route(domain::path::Paths::Metrics.as_str(), get(routes::metrics))
+            .nest_service(domain::path::Paths::Root.as_str(), ServeDir::new("assets"));
+
+        if settings.environment.is_local() {
+            router = router.nest(
+                domain::path::Paths::DevMailbox.as_str(),
+                routes::dev_mailbox_router(settings.email.mailbox.clone()),
+            );
+        }
+
+        let metrics = app_state.metrics.clone();
+        let router = router
             .with_state(app_state)
-            .layer(cors)
+            .layer(CsrfLayer::new(settings.auth_cookie.clone()))
+            .layer(middleware::from_fn_with_state(metrics, track_http_metrics))
+            .layer(settings.cors.layer())
             .layer(
                 TraceLayer::new_for_http()
                     .make_span_with(make_span_with_request_id)
                     .on_request(on_request)
                     .on_response(on_response),
-            );
+            )
+            .layer(middleware::from_fn(propagate_request_id));
 
-        let listener = tokio::net::TcpListener::bind(address).await?;
+        let listener = TcpListener::bind(&settings.application.address).await?;
         let address = listener.local_addr()?.to_string();
         let server = axum::serve(listener, router);
 
+        let grpc_listener = TcpListener::bind(&settings.application.grpc_address).await?;
+        let grpc_address = grpc_listener.local_addr()?.to_string();
+
         // Create a new Application instance and return it
         Ok(Self {
             address: address.to_string(),
+            grpc_address,
             server,
+            grpc_listener,
+            grpc_service,
+            email_outbox_worker,
+            expiry_purge_worker,
+            settings,
+            shutdown,
         })
     }
 
+    // Serves until Ctrl+C or SIGTERM, then shuts down gracefully
     pub async fn run(self) -> Result<(), std::io::Error> {
+        self.run_until(shutdown_signal()).await
+    }
+
+    // Serves until `signal` resolves, then:
+    // 1. fails the readiness probe for `shutdown.readiness_delay`, still serving requests,
+    // 2. stops accepting connections and lets in-flight requests finish,
+    // 3. stops the background workers.
+    // Steps 2 and 3 get `shutdown.drain_timeout` in total, whatever is left is dropped.
+    pub async fn run_until(
+        self,
+        signal: impl Future<Output = ()> + Send + 'static,
+    ) -> Result<(), std::io::Error> {
         tracing::info!("listening on {}", &self.address);
-        self.server.await?;
+        tracing::info!("gRPC listening on {}", &self.grpc_address);
+
+        let stop_servers = CancellationToken::new();
+        let stop_workers = CancellationToken::new();
+        let email_outbox_worker = tokio::spawn(self.email_outbox_worker.run(stop_workers.clone()));
+        let expiry_purge_worker = self
+            .expiry_purge_worker
+            .map(|worker| tokio::spawn(worker.run(stop_workers.clone())));
 
+        let http_server = self
+            .server
+            .with_graceful_shutdown(stop_servers.clone().cancelled_owned());
+        let grpc_server = tonic::transport::Server::builder()
+            .trace_fn(make_grpc_span)
+            .add_service(AuthServer::new(self.grpc_service))
+            .serve_with_incoming_shutdown(
+                TcpListenerStream::new(self.grpc_listener),
+                stop_servers.clone().cancelled_owned(),
+            );
+
+        // Both servers share the same state, stop as soon as one of them fails
+        let servers = async {
+            tokio::try_join!(async { http_server.await }, async {
+                grpc_server.await.map_err(std::io::Error::other)
+            })
+        };
+        tokio::pin!(servers);
+
+        tokio::select! {
+            result = &mut servers => {
+                stop_workers.cancel();
+                result?;
+                return Ok(());
+            }
+            _ = signal => {}
+        }
+
+        let settings = &self.settings.shutdown;
+        tracing::info!(
+            "Shutting down, failing the readiness probe for {:?}",
+            settings.readiness_delay
+        );
+        self.shutdown.cancel();
+        tokio::select! {
+            result = &mut servers => {
+                stop_workers.cancel();
+                result?;
+                return Ok(());
+            }
+            _ = tokio::time::sleep(settings.readiness_delay) => {}
+        }
+
+        tracing::info!(
+            "Draining in-flight requests for up to {:?}",
+            settings.drain_timeout
+        );
+        let deadline = Instant::now() + settings.drain_timeout;
+        stop_servers.cancel();
+        match tokio::time::timeout_at(deadline, &mut servers).await {
+            Ok(result) => {
+                result?;
+            }
+            Err(_) => {
+                tracing::warn!("Requests still in flight after the drain timeout, dropping them")
+            }
+        }
+
+        stop_workers.cancel();
+        // Both were cancelled, they stop concurrently whichever is awaited first
+        let workers = async {
+            let _ = email_outbox_worker.await;
+            if let Some(expiry_purge_worker) = expiry_purge_worker {
+                let _ = expiry_purge_worker.await;
+            }
+        };
+        if tokio::time::timeout_at(deadline, workers).await.is_err() {
+            tracing::warn!("Workers still busy after the drain timeout, dropping them");
+        }
+
+        tracing::info!("Servers and workers stopped");
         Ok(())
     }
 }
@@ -91,31 +245,33 @@ impl Application {
 #[derive(Serialize, Deserialize)]
 pub struct ErrorResponse {
     pub error: String,
+    pub code: ErrorCode,
 }
 
 impl IntoResponse for AuthAPIError {
     fn into_response(self) -> axum::response::Response {
         log_error_chain(&self);
 
-        let (status, error_message) = match self {
-            AuthAPIError::UserAlreadyExists => (StatusCode::CONFLICT, "User already exists"),
-            AuthAPIError::InvalidCredentials => (StatusCode::BAD_REQUEST, "Invalid credentials"),
-            AuthAPIError::UnexpectedError(_) => {
-                (StatusCode::INTERNAL_SERVER_ERROR, "Unexpected error")
-            }
-            AuthAPIError::InvalidRecaptcha => (StatusCode::BAD_REQUEST, "Invalid captcha"),
-            AuthAPIError::IncorrectCredentials => (StatusCode::UNAUTHORIZED, "Unauthorized"),
-            AuthAPIError::MissingToken => (StatusCode::BAD_REQUEST, "Missing auth token"),
-            AuthAPIError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid auth token"),
+        let code = self.code();
+        let status = match code {
+            ErrorCode::UserAlreadyExists => StatusCode::CONFLICT,
+            ErrorCode::InvalidCredentials => StatusCode::BAD_REQUEST,
+            ErrorCode::UnexpectedError => StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorCode::InvalidRecaptcha => StatusCode::BAD_REQUEST,
+            ErrorCode::IncorrectCredentials => StatusCode::UNAUTHORIZED,
+            ErrorCode::MissingToken => StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidToken => StatusCode::UNAUTHORIZED,
+            ErrorCode::InvalidCsrfToken => StatusCode::FORBIDDEN,
         };
         let body = Json(ErrorResponse {
-            error: error_message.to_owned(),
+            error: code.message().to_owned(),
+            code,
         });
         (status, body).into_response()
     }
 }
 
-fn log_error_chain(e: &(dyn Error + 'static)) {
+pub(crate) fn log_error_chain(e: &(dyn Error + 'static)) {
     let separator =
         "\n-----------------------------------------------------------------------------------\n";
     let mut report = format!("{}{:?}\n", separator, e);
@@ -137,7 +293,7 @@ pub async fn get_postgres_pool(url: &Secret<String>) -> Result<PgPool, sqlx::Err
         .await
 }
 
-pub fn get_redis_client(redis_hostname: String) -> RedisResult<RedisClient> {
-    let redis_url = format!("redis://{}/", redis_hostname);
-    redis::Client::open(redis_url)
+// Connection shared by every Redis store, to a server, through Sentinel or to a cluster
+pub async fn get_redis_connection(settings: &RedisSettings) -> RedisResult<RedisConnection> {
+    RedisConnection::connect(settings).await
 }
@@ -1,53 +1,93 @@
-use auth_service::app_state::AppState;
-use auth_service::domain::Email;
+use auth_service::app_state::{
+    AppState, BannedTokenStoreType, EmailClientType, HealthChecksType, TwoFACodeStoreType,
+};
+use auth_service::domain::{Email, HealthCheck};
 use auth_service::services::postgres_user_store::PostgresUserStore;
 use auth_service::services::{
-    aws_ses_email_client::SESEmailClient, data_stores::RedisBannedTokenStore,
+    aws_ses_email_client::SESEmailClient,
+    data_stores::PostgresBannedTokenStore,
+    data_stores::PostgresEmailOutboxStore,
+    data_stores::PostgresTwoFACodeStore,
+    data_stores::RedisBannedTokenStore,
+    data_stores::RedisConnection,
     data_stores::RedisTwoFACodeStore,
+    file_email_client::FileEmailClient,
+    health_checks::{EmailHealthCheck, PostgresHealthCheck, RedisHealthCheck},
+    smtp_email_client::SmtpEmailClient,
+};
+use auth_service::settings::{EmailClientSettings, RedisSettings, Settings, StoreBackend};
+use auth_service::utils::{
+    keyed_hash::KeyedHasher,
+    metrics::Metrics,
+    tracing::{init_tracing, shutdown_tracing},
 };
-use auth_service::utils::constants::{prod, DATABASE_URL, REDIS_HOST_NAME};
-use auth_service::utils::tracing::init_tracing;
-use auth_service::{get_postgres_pool, get_redis_client, Application};
+use auth_service::{get_postgres_pool, get_redis_connection, Application};
 use aws_config::{meta::region::RegionProviderChain, BehaviorVersion};
 use dotenvy::dotenv;
+use prometheus::Registry;
 use secrecy::Secret;
 use sqlx::PgPool;
 use std::sync::Arc;
-use tokio::sync::RwLock;
 
 #[tokio::main]
 async fn main() {
     dotenv().ok();
     color_eyre::install().expect("Failed to install color_eyre");
-    init_tracing().expect("Failed to initialize tracing");
 
-    let pg_pool = configure_postgresql().await;
-    let redis_connection = Arc::new(RwLock::new(configure_redis()));
+    // Fail right away on a bad configuration rather than on the first request that needs it.
+    // Loaded before tracing, which takes its format from it.
+    let settings = match Settings::load() {
+        Ok(settings) => Arc::new(settings),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    init_tracing(settings.logging.format).expect("Failed to initialize tracing");
+
+    let pg_pool = configure_postgresql(&settings.database.url).await;
 
-    let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
-    let banned_token_store = Arc::new(RwLock::new(RedisBannedTokenStore::new(
-        redis_connection.clone(),
-    )));
-    let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_connection)));
-    let email_client = Arc::new(configure_ses_email_client().await);
+    let user_store = Arc::new(PostgresUserStore::new(pg_pool.clone()));
+    let (banned_token_store, two_fa_code_store, redis_connection) =
+        configure_stores(&settings, &pg_pool).await;
+    let email_client = configure_email_client(&settings).await;
+    let email_outbox = Arc::new(PostgresEmailOutboxStore::new(pg_pool.clone()));
+    let health_checks = configure_health_checks(
+        &settings,
+        pg_pool.clone(),
+        redis_connection,
+        email_client.clone(),
+    );
+    let metrics = configure_metrics(pg_pool.clone());
 
     let app_state = AppState::new(
         user_store,
         banned_token_store,
         two_fa_code_store,
         email_client,
+        email_outbox,
+        health_checks,
+        metrics,
+        settings,
     );
 
-    let app = Application::build(app_state, prod::APP_ADDRESS)
+    let app = Application::build(app_state)
         .await
         .expect("Failed to build app");
 
     app.run().await.expect("Failed to run app");
+
+    // The servers and workers are stopped, nothing uses the pool anymore. The Redis connection
+    // has no close, it goes away with the process.
+    pg_pool.close().await;
+    tracing::info!("Closed the Postgres pool");
+
+    shutdown_tracing();
 }
 
-async fn configure_postgresql() -> PgPool {
+async fn configure_postgresql(database_url: &Secret<String>) -> PgPool {
     // Create a new database connection pool.
-    let pg_pool = get_postgres_pool(&DATABASE_URL)
+    let pg_pool = get_postgres_pool(database_url)
         .await
         .expect("Failed to create Postgres connection pool!");
 
@@ -59,11 +99,99 @@ async fn configure_postgresql() -> PgPool {
     pg_pool
 }
 
-fn configure_redis() -> redis::Connection {
-    get_redis_client(REDIS_HOST_NAME.to_owned())
-        .expect("Failed to get Redis client")
-        .get_connection()
-        .expect("Failed to get Redis connection")
+async fn configure_redis(redis_settings: &RedisSettings) -> RedisConnection {
+    get_redis_connection(redis_settings)
+        .await
+        .expect("Failed to connect to Redis")
+}
+
+// Banned tokens and 2FA codes in Redis, or in Postgres for deployments without Redis, which
+// is then not connected to
+async fn configure_stores(
+    settings: &Settings,
+    pg_pool: &PgPool,
+) -> (
+    BannedTokenStoreType,
+    TwoFACodeStoreType,
+    Option<RedisConnection>,
+) {
+    let hasher = KeyedHasher::from_jwt_secret(&settings.jwt.secret);
+
+    match &settings.stores.backend {
+        StoreBackend::Redis(redis_settings) => {
+            let redis_connection = configure_redis(redis_settings).await;
+            let banned_token_store = Arc::new(RedisBannedTokenStore::new(
+                redis_connection.clone(),
+                redis_settings.key_prefix.clone(),
+                hasher.clone(),
+            ));
+            let two_fa_code_store = Arc::new(RedisTwoFACodeStore::new(
+                redis_connection.clone(),
+                redis_settings.key_prefix.clone(),
+                hasher,
+            ));
+            migrate_legacy_redis_keys(&banned_token_store, &two_fa_code_store).await;
+
+            (
+                banned_token_store,
+                two_fa_code_store,
+                Some(redis_connection),
+            )
+        }
+        StoreBackend::Postgres => (
+            Arc::new(PostgresBannedTokenStore::new(
+                pg_pool.clone(),
+                hasher.clone(),
+            )),
+            Arc::new(PostgresTwoFACodeStore::new(pg_pool.clone(), hasher)),
+            None,
+        ),
+    }
+}
+
+// Hashes what earlier versions stored in plain text. A failure isn't fatal, the stores still
+// read the legacy keys until they expire.
+async fn migrate_legacy_redis_keys(
+    banned_token_store: &RedisBannedTokenStore,
+    two_fa_code_store: &RedisTwoFACodeStore,
+) {
+    match banned_token_store.migrate_legacy_keys().await {
+        Ok(0) => {}
+        Ok(count) => tracing::info!(count, "Hashed the keys of legacy banned tokens"),
+        Err(e) => tracing::warn!(error = ?e, "Failed to hash the keys of legacy banned tokens"),
+    }
+    match two_fa_code_store.migrate_legacy_codes().await {
+        Ok(0) => {}
+        Ok(count) => tracing::info!(count, "Hashed legacy 2FA codes"),
+        Err(e) => tracing::warn!(error = ?e, "Failed to hash legacy 2FA codes"),
+    }
+}
+
+fn configure_health_checks(
+    settings: &Settings,
+    pg_pool: PgPool,
+    redis_connection: Option<RedisConnection>,
+    email_client: EmailClientType,
+) -> HealthChecksType {
+    let mut health_checks: Vec<Arc<dyn HealthCheck + Send + Sync>> =
+        vec![Arc::new(PostgresHealthCheck::new(pg_pool))];
+    if let Some(redis_connection) = redis_connection {
+        health_checks.push(Arc::new(RedisHealthCheck::new(redis_connection)));
+    }
+    if settings.health.check_email_provider {
+        health_checks.push(Arc::new(EmailHealthCheck::new(email_client)));
+    }
+
+    Arc::new(health_checks)
+}
+
+fn configure_metrics(pg_pool: PgPool) -> Arc<Metrics> {
+    let metrics = Metrics::new(Registry::new()).expect("Failed to register metrics");
+    metrics
+        .observe_postgres_pool(pg_pool)
+        .expect("Failed to register Postgres pool metrics");
+
+    Arc::new(metrics)
 }
 
 async fn configure_aws_config() -> aws_config::SdkConfig {
@@ -75,12 +203,23 @@ async fn configure_aws_config() -> aws_config::SdkConfig {
         .await
 }
 
-async fn configure_ses_email_client() -> SESEmailClient {
+async fn configure_email_client(settings: &Settings) -> EmailClientType {
+    let sender = settings.email.sender.clone();
+
+    match &settings.email.client {
+        EmailClientSettings::Ses => Arc::new(configure_ses_email_client(sender).await),
+        EmailClientSettings::Smtp(smtp_settings) => Arc::new(
+            SmtpEmailClient::new(sender, smtp_settings.clone())
+                .expect("Failed to create SMTP email client"),
+        ),
+        EmailClientSettings::File => {
+            Arc::new(FileEmailClient::new(sender, settings.email.mailbox.clone()))
+        }
+    }
+}
+
+async fn configure_ses_email_client(sender: Email) -> SESEmailClient {
     let sdk_config = configure_aws_config().await;
-    let sender = Email::parse(Secret::new(
-        auth_service::utils::constants::EMAIL_SENDER.to_owned(),
-    ))
-    .expect("Failed to create Email from EMAIL_SENDER env");
 
     SESEmailClient::new(sender, &sdk_config)
 }
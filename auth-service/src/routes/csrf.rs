@@ -0,0 +1,30 @@
+use crate::{
+    app_state::AppState,
+    utils::csrf::{csrf_cookie_token, generate_csrf_token},
+};
+use axum::{extract::State, http::HeaderMap, Json};
+use axum_extra::extract::CookieJar;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CsrfResponse {
+    // Must be sent back in the `X-CSRF-Token` header of state-changing requests
+    #[serde(rename = "csrfToken")]
+    pub csrf_token: String,
+}
+
+// Hands out the CSRF token and sets it as a cookie. An existing token is kept, so tabs that
+// already fetched it keep working.
+#[tracing::instrument(name = "CSRF Route Handler", skip_all)]
+pub async fn csrf(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    jar: CookieJar,
+) -> (CookieJar, Json<CsrfResponse>) {
+    let cookie_settings = &state.settings.auth_cookie;
+    let csrf_token =
+        csrf_cookie_token(&headers, cookie_settings).unwrap_or_else(generate_csrf_token);
+    let jar = jar.add(cookie_settings.build_csrf(csrf_token.clone()));
+
+    (jar, Json(CsrfResponse { csrf_token }))
+}
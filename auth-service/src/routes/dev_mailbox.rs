@@ -0,0 +1,66 @@
+use crate::{
+    domain::AuthAPIError,
+    services::file_email_client::{FileMailbox, MailboxMessage},
+};
+use askama::Template;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use color_eyre::eyre::Report;
+
+#[derive(Template)]
+#[template(path = "dev/mailbox.html")]
+struct MailboxTemplate {
+    messages: Vec<MailboxMessage>,
+}
+
+#[derive(Template)]
+#[template(path = "dev/mailbox_message.html")]
+struct MailboxMessageTemplate {
+    message: MailboxMessage,
+}
+
+// Browses the emails written by `FileEmailClient`. Only mounted in local environments.
+pub fn dev_mailbox_router<S>(mailbox: FileMailbox) -> Router<S> {
+    Router::new()
+        .route("/", get(list_messages))
+        .route("/:id", get(show_message))
+        .with_state(mailbox)
+}
+
+#[tracing::instrument(name = "Dev Mailbox Route Handler", skip_all)]
+async fn list_messages(State(mailbox): State<FileMailbox>) -> Result<Html<String>, AuthAPIError> {
+    let messages = mailbox
+        .list()
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    render(MailboxTemplate { messages })
+}
+
+#[tracing::instrument(name = "Dev Mailbox Message Route Handler", skip_all)]
+async fn show_message(
+    Path(id): Path<String>,
+    State(mailbox): State<FileMailbox>,
+) -> Result<Response, AuthAPIError> {
+    let message = mailbox
+        .read(&id)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    match message {
+        Some(message) => Ok(render(MailboxMessageTemplate { message })?.into_response()),
+        None => Ok((StatusCode::NOT_FOUND, "Email not found").into_response()),
+    }
+}
+
+fn render(template: impl Template) -> Result<Html<String>, AuthAPIError> {
+    template
+        .render()
+        .map(Html)
+        .map_err(|e| AuthAPIError::UnexpectedError(Report::new(e)))
+}
@@ -0,0 +1,74 @@
+use crate::{app_state::AppState, domain::check_dependencies};
+use axum::{extract::State, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Ok,
+    Unavailable,
+    // Shutting down, finishing in-flight requests
+    Draining,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LivenessResponse {
+    pub status: HealthStatus,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReadinessResponse {
+    pub status: HealthStatus,
+    // Keyed by dependency name, e.g. `postgres`
+    pub checks: BTreeMap<String, DependencyStatus>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DependencyStatus {
+    pub status: HealthStatus,
+    #[serde(rename = "latencyMs")]
+    pub latency_ms: u64,
+}
+
+// The process is up and serving requests, restarting it won't fix a dependency outage
+pub async fn live() -> Json<LivenessResponse> {
+    Json(LivenessResponse {
+        status: HealthStatus::Ok,
+    })
+}
+
+// Whether the service can handle traffic right now, 503 as soon as one dependency is down or
+// once it's shutting down
+pub async fn ready(State(state): State<AppState>) -> (StatusCode, Json<ReadinessResponse>) {
+    if state.shutdown.is_cancelled() {
+        let response = ReadinessResponse {
+            status: HealthStatus::Draining,
+            checks: BTreeMap::new(),
+        };
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(response));
+    }
+
+    let report = check_dependencies(&state.health_checks, state.settings.health.timeout).await;
+
+    let checks: BTreeMap<String, DependencyStatus> = report
+        .iter()
+        .map(|health| {
+            let status = DependencyStatus {
+                status: match health.healthy {
+                    true => HealthStatus::Ok,
+                    false => HealthStatus::Unavailable,
+                },
+                latency_ms: health.latency.as_millis() as u64,
+            };
+            (health.name.to_owned(), status)
+        })
+        .collect();
+
+    let (status_code, status) = match report.iter().all(|health| health.healthy) {
+        true => (StatusCode::OK, HealthStatus::Ok),
+        false => (StatusCode::SERVICE_UNAVAILABLE, HealthStatus::Unavailable),
+    };
+
+    (status_code, Json(ReadinessResponse { status, checks }))
+}
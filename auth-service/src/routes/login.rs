@@ -1,11 +1,19 @@
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email, LoginAttemptId, Password, TwoFACode},
-    utils::auth::generate_auth_cookie,
+    domain::{AuthAPIError, Email, Locale, LoginAttemptId, OutboxEmail, Password, TwoFACode},
+    services::{
+        email_outbox::deliver_email,
+        email_templates::{EmailTemplate, TwoFACodeEmail},
+    },
+    utils::auth::{create_auth_cookie, generate_auth_token},
+};
+use axum::{
+    extract::State,
+    http::{header::ACCEPT_LANGUAGE, HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
 };
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use axum_extra::extract::CookieJar;
-use color_eyre::eyre::Result;
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
 
@@ -13,6 +21,12 @@ use serde::{Deserialize, Serialize};
 pub struct LoginRequest {
     pub email: String,
     pub password: Secret<String>,
+    // Preferred language for emails sent during this login, overrides `Accept-Language`
+    #[serde(default)]
+    pub locale: Option<String>,
+    // Also return the JWT in the body, for clients that can't use cookies
+    #[serde(default, rename = "returnToken")]
+    pub return_token: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -20,6 +34,12 @@ pub struct LoginRequest {
 pub enum LoginResponse {
     RegularAuth,
     TwoFactorAuth(TwoFactorAuthResponse),
+    Token(TokenResponse),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenResponse {
+    pub token: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -29,95 +49,140 @@ pub struct TwoFactorAuthResponse {
     pub login_attempt_id: String,
 }
 
+// Result of checking a user's credentials.
+#[derive(Debug)]
+pub enum LoginOutcome {
+    // The credentials were enough, holds the JWT of the user
+    Authenticated(String),
+    // A 2FA code was sent to the user, who has to verify it to get a token
+    TwoFactorRequired(LoginAttemptId),
+}
+
 #[tracing::instrument(name = "Login Route Handler", skip_all)]
 pub async fn login(
     State(state): State<AppState>,
     jar: CookieJar,
+    headers: HeaderMap,
     Json(request): Json<LoginRequest>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
-    let password = match Password::parse(request.password) {
-        Ok(password) => password,
-        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
-    };
+    let locale = Locale::negotiate(
+        request.locale.as_deref(),
+        headers
+            .get(ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok()),
+    );
+
+    match authenticate_user(&state, request.email, request.password, locale).await {
+        Ok(LoginOutcome::Authenticated(token)) => {
+            let response = match request.return_token {
+                true => LoginResponse::Token(TokenResponse {
+                    token: token.clone(),
+                }),
+                false => LoginResponse::RegularAuth,
+            };
+
+            (
+                jar.add(create_auth_cookie(&state.settings.auth_cookie, token)),
+                Ok((StatusCode::OK, Json(response))),
+            )
+        }
+        Ok(LoginOutcome::TwoFactorRequired(login_attempt_id)) => {
+            let response = Json(LoginResponse::TwoFactorAuth(TwoFactorAuthResponse {
+                message: "2FA required".to_string(),
+                login_attempt_id: login_attempt_id.as_ref().expose_secret().to_string(),
+            }));
+
+            (jar, Ok((StatusCode::PARTIAL_CONTENT, response)))
+        }
+        Err(e) => (jar, Err(e)),
+    }
+}
 
-    let email = match Email::parse(Secret::new(request.email)) {
-        Ok(email) => email,
-        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
-    };
+// Checks the user's credentials, shared by the HTTP and gRPC APIs.
+#[tracing::instrument(name = "Authenticate User", skip_all)]
+pub async fn authenticate_user(
+    state: &AppState,
+    email: String,
+    password: Secret<String>,
+    locale: Locale,
+) -> Result<LoginOutcome, AuthAPIError> {
+    let result = check_credentials(state, email, password, locale).await;
+    state.metrics.record_login(&result);
+
+    result
+}
+
+async fn check_credentials(
+    state: &AppState,
+    email: String,
+    password: Secret<String>,
+    locale: Locale,
+) -> Result<LoginOutcome, AuthAPIError> {
+    let password = Password::parse(password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let email = Email::parse(Secret::new(email)).map_err(|_| AuthAPIError::InvalidCredentials)?;
 
-    let user_store = &state.user_store.read().await;
+    let user_store = &state.user_store;
 
     if user_store.validate_user(&email, &password).await.is_err() {
-        return (jar, Err(AuthAPIError::IncorrectCredentials));
+        return Err(AuthAPIError::IncorrectCredentials);
     }
 
-    let user = match user_store.get_user(&email).await {
-        Ok(user) => user,
-        Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
-    };
+    let user = user_store
+        .get_user(&email)
+        .await
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
 
     match user.requires_2fa {
-        true => handle_2fa(&user.email, &state, jar).await,
-        false => handle_no_2fa(&user.email, jar).await,
+        true => handle_2fa(&user.email, locale, state).await,
+        false => handle_no_2fa(&user.email, state),
     }
 }
 
 #[tracing::instrument(name = "Handle 2FA", skip_all)]
 async fn handle_2fa(
     email: &Email,
+    locale: Locale,
     state: &AppState,
-    jar: CookieJar,
-) -> (
-    CookieJar,
-    Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
-) {
+) -> Result<LoginOutcome, AuthAPIError> {
     let login_attempt_id = LoginAttemptId::default();
     let two_fa_code = TwoFACode::default();
 
-    if let Err(e) = state
+    state
         .two_fa_code_store
-        .write()
-        .await
         .add_code(email.clone(), login_attempt_id.clone(), two_fa_code.clone())
         .await
-    {
-        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
-    }
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
 
-    let content = format!("The 2FA code requested is: {}", two_fa_code);
-    if let Err(e) = state
-        .email_client
-        .send_email(email, "2FA code", &content)
-        .await
-    {
-        return (jar, Err(AuthAPIError::UnexpectedError(e)));
+    let message = (TwoFACodeEmail { code: two_fa_code })
+        .render(locale)
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let outbox_email = OutboxEmail {
+        // One 2FA email per login attempt, even if the request is retried
+        idempotency_key: format!("two_fa_code:{}", login_attempt_id.as_ref().expose_secret()),
+        recipient: email.clone(),
+        message,
     };
 
-    let response = Json(LoginResponse::TwoFactorAuth(TwoFactorAuthResponse {
-        message: "2FA required".to_string(),
-        login_attempt_id: login_attempt_id.as_ref().expose_secret().to_string(),
-    }));
+    deliver_email(
+        &state.email_outbox,
+        &state.email_client,
+        &state.metrics,
+        outbox_email,
+        state.settings.email.two_fa_delivery,
+    )
+    .await
+    .map_err(AuthAPIError::UnexpectedError)?;
+
+    state.metrics.record_two_fa_code_issued();
 
-    (jar, Ok((StatusCode::PARTIAL_CONTENT, response)))
+    Ok(LoginOutcome::TwoFactorRequired(login_attempt_id))
 }
 
 #[tracing::instrument(name = "Handle No 2FA", skip_all)]
-async fn handle_no_2fa(
-    email: &Email,
-    jar: CookieJar,
-) -> (
-    CookieJar,
-    Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
-) {
-    let auth_cookie = match generate_auth_cookie(email) {
-        Ok(cookie) => cookie,
-        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
-    };
-
-    let updated_jar = jar.add(auth_cookie);
+fn handle_no_2fa(email: &Email, state: &AppState) -> Result<LoginOutcome, AuthAPIError> {
+    let token = generate_auth_token(email, &state.settings.jwt.secret)
+        .map_err(AuthAPIError::UnexpectedError)?;
 
-    (
-        updated_jar,
-        Ok((StatusCode::OK, Json(LoginResponse::RegularAuth))),
-    )
+    Ok(LoginOutcome::Authenticated(token))
 }
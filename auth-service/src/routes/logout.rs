@@ -1,7 +1,7 @@
 use crate::{
     app_state::AppState,
-    domain::AuthAPIError,
-    utils::{auth, constants::JWT_COOKIE_NAME},
+    domain::{data_stores::BannedTokenStoreError, AuthAPIError},
+    utils::{auth, extractors::AuthToken},
 };
 use axum::{extract::State, http::StatusCode, response::IntoResponse};
 use axum_extra::extract::CookieJar;
@@ -11,31 +11,43 @@ use secrecy::Secret;
 pub async fn logout(
     jar: CookieJar,
     State(state): State<AppState>,
+    AuthToken(token): AuthToken,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
-    let cookie = jar.get(JWT_COOKIE_NAME);
-
-    if cookie.is_none() {
-        return (jar, Err(AuthAPIError::MissingToken));
+    if let Err(e) = revoke_token(&state, token).await {
+        return (jar, Err(e));
     }
 
-    let cookie = cookie.unwrap(); // @todo: Compare with teacher's codebase if there's an easier way to extract this without unwrap
-    let token = cookie.value().to_string();
-    let banned_token_store = state.banned_token_store.clone();
-
-    let response = auth::validate_token(&token, banned_token_store).await;
+    // Removes cookie
+    let jar = jar.remove(auth::remove_auth_cookie(&state.settings.auth_cookie));
 
-    if let Err(_err) = response {
-        return (jar, Err(AuthAPIError::InvalidToken));
-    }
+    (jar, Ok(StatusCode::OK))
+}
 
-    // Removes cookie
-    let jar = jar.remove(axum_extra::extract::cookie::Cookie::from(JWT_COOKIE_NAME));
+// Bans a valid token so it can't be used again, shared by the HTTP and gRPC APIs.
+#[tracing::instrument(name = "Revoke Token", skip_all)]
+pub async fn revoke_token(state: &AppState, token: String) -> Result<(), AuthAPIError> {
+    let Ok(claims) = auth::validate_token(
+        &token,
+        &state.settings.jwt.secret,
+        state.banned_token_store.clone(),
+    )
+    .await
+    else {
+        return Err(AuthAPIError::InvalidToken);
+    };
 
     // Add token to banned token store
-    let mut banned_token_store = state.banned_token_store.write().await;
-    if let Err(e) = banned_token_store.add_token(Secret::new(token)).await {
-        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
-    }
-
-    (jar, Ok(StatusCode::OK))
+    state
+        .banned_token_store
+        .add_token(Secret::new(token), claims.exp)
+        .await
+        .map_err(|e| match e {
+            // Expired since it was validated
+            BannedTokenStoreError::TokenExpired => AuthAPIError::InvalidToken,
+            e => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+
+    state.metrics.record_token_banned();
+
+    Ok(())
 }
@@ -0,0 +1,17 @@
+use crate::{app_state::AppState, domain::check_dependencies, domain::AuthAPIError};
+use axum::{extract::State, http::header::CONTENT_TYPE, response::IntoResponse};
+use color_eyre::eyre::eyre;
+use prometheus::TEXT_FORMAT;
+
+// Prometheus scrape endpoint. The dependency gauges are refreshed on every scrape.
+pub async fn metrics(State(state): State<AppState>) -> Result<impl IntoResponse, AuthAPIError> {
+    let report = check_dependencies(&state.health_checks, state.settings.health.timeout).await;
+    state.metrics.record_dependencies(&report);
+
+    let body = state
+        .metrics
+        .render()
+        .map_err(|e| AuthAPIError::UnexpectedError(eyre!(e)))?;
+
+    Ok(([(CONTENT_TYPE, TEXT_FORMAT)], body))
+}
@@ -1,13 +1,23 @@
+mod csrf;
+mod dev_mailbox;
+mod health;
 mod login;
 mod logout;
+mod metrics;
+mod session;
 mod signup;
 mod users;
 mod verify_2fa;
 mod verify_token;
 
 // Re-export items from sub-modules;
+pub use csrf::*;
+pub use dev_mailbox::*;
+pub use health::*;
 pub use login::*;
 pub use logout::*;
+pub use metrics::*;
+pub use session::*;
 pub use signup::*;
 pub use users::*;
 pub use verify_2fa::*;
@@ -0,0 +1,19 @@
+use crate::utils::extractors::AuthenticatedUser;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionResponse {
+    pub email: String,
+    // Unix timestamp in seconds after which the token is no longer accepted
+    #[serde(rename = "expiresAt")]
+    pub expires_at: usize,
+}
+
+#[tracing::instrument(name = "Session Route Handler", skip_all)]
+pub async fn session(user: AuthenticatedUser) -> Json<SessionResponse> {
+    Json(SessionResponse {
+        email: user.claims.sub,
+        expires_at: user.claims.exp,
+    })
+}
@@ -1,10 +1,9 @@
 use crate::{
     app_state::AppState,
-    domain::{environment::get_env, AuthAPIError, Email, Password, User},
-    utils::constants::env::RECAPTCHA_SECRET_ENV_VAR,
+    domain::{data_stores::UserStoreError, AuthAPIError, Email, Password, User},
 };
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
-use secrecy::Secret;
+use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -33,12 +32,12 @@ pub struct SignupRequest {
     pub recaptcha: String,
 }
 
-async fn validate_recaptcha(token: String) -> bool {
-    let recaptcha_secret = get_env(RECAPTCHA_SECRET_ENV_VAR);
+async fn validate_recaptcha(recaptcha_secret: &Secret<String>, token: String) -> bool {
+    let recaptcha_secret = recaptcha_secret.expose_secret();
     let mut params = HashMap::new();
     let mut headers = reqwest::header::HeaderMap::new();
 
-    params.insert("secret", &recaptcha_secret);
+    params.insert("secret", recaptcha_secret);
     params.insert("response", &token);
 
     let api_client = reqwest::Client::builder().build().unwrap();
@@ -70,33 +69,55 @@ pub async fn signup(
     State(state): State<AppState>,
     Json(request): Json<SignupRequest>,
 ) -> Result<impl IntoResponse, AuthAPIError> {
-    // Create a new `User` instance using data in the `request`
-    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
-    let password =
-        Password::parse(request.password).map_err(|_| AuthAPIError::InvalidCredentials)?;
-    let requires_2fa = request.requires_2fa;
+    register_user(
+        &state,
+        request.email,
+        request.password,
+        request.requires_2fa,
+        request.recaptcha,
+    )
+    .await?;
 
-    let is_recaptcha_valid = validate_recaptcha(request.recaptcha).await;
+    let response = Json(SignupResponse {
+        message: "User created successfully!".to_string(),
+    });
+
+    Ok((StatusCode::CREATED, response))
+}
+
+// Creates a new user, shared by the HTTP and gRPC APIs.
+#[tracing::instrument(name = "Register User", skip_all)]
+pub async fn register_user(
+    state: &AppState,
+    email: Secret<String>,
+    password: Secret<String>,
+    requires_2fa: bool,
+    recaptcha: String,
+) -> Result<(), AuthAPIError> {
+    let email = Email::parse(email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let password = Password::parse(password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let is_recaptcha_valid = validate_recaptcha(&state.settings.recaptcha.secret, recaptcha).await;
 
     if !is_recaptcha_valid {
         return Err(AuthAPIError::InvalidRecaptcha);
     }
 
-    let mut user_store = state.user_store.write().await;
     let user = User::new(email, password, requires_2fa);
 
-    // If user already exists then return 409
-    if user_store.get_user(&user.email).await.is_ok() {
+    // If user already exists then return 409, without hashing the password
+    if state.user_store.get_user(&user.email).await.is_ok() {
         return Err(AuthAPIError::UserAlreadyExists);
     }
 
-    if let Err(e) = user_store.add_user(user).await {
-        return Err(AuthAPIError::UnexpectedError(e.into()));
+    // Concurrent signups for the same email race to this point, the store lets only one in
+    match state.user_store.add_user(user).await {
+        Ok(()) => {}
+        Err(UserStoreError::UserAlreadyExists) => return Err(AuthAPIError::UserAlreadyExists),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
     }
 
-    let response = Json(SignupResponse {
-        message: "User created successfully!".to_string(),
-    });
+    state.metrics.record_signup();
 
-    Ok((StatusCode::CREATED, response))
+    Ok(())
 }
@@ -15,18 +15,33 @@ pub async fn delete(
     Path(request_email): Path<Secret<String>>,
     State(state): State<AppState>,
 ) -> Result<impl IntoResponse, AuthAPIError> {
-    let email = Email::parse(request_email).map_err(|_| AuthAPIError::InvalidCredentials)?;
-    let mut user_store = state.user_store.write().await;
+    delete_user_account(&state, request_email).await?;
 
-    if let Ok(user) = user_store.get_user(&email).await {
-        let _ = user_store.delete_user(user).await;
-        let response = Json(DeleteUserResponse {
-            message: "User deleted successfully!".to_string(),
-        });
-        Ok((StatusCode::OK, response))
-    } else {
-        Err(AuthAPIError::InvalidCredentials)
-    }
+    let response = Json(DeleteUserResponse {
+        message: "User deleted successfully!".to_string(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+// Deletes an existing user, shared by the HTTP and gRPC APIs.
+#[tracing::instrument(name = "Delete User Account", skip_all)]
+pub async fn delete_user_account(
+    state: &AppState,
+    email: Secret<String>,
+) -> Result<(), AuthAPIError> {
+    let email = Email::parse(email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let user = state
+        .user_store
+        .get_user(&email)
+        .await
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    state
+        .user_store
+        .delete_user(user)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
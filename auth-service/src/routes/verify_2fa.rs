@@ -1,11 +1,15 @@
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email},
+    domain::{
+        data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStoreError},
+        AuthAPIError, Email,
+    },
+    routes::TokenResponse,
     utils::auth,
 };
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use axum_extra::extract::CookieJar;
-use secrecy::{ExposeSecret, Secret};
+use secrecy::Secret;
 use serde::Deserialize;
 
 #[derive(Deserialize, Debug)]
@@ -15,6 +19,9 @@ pub struct Verify2FARequest {
     pub two_fa_code: String,
     #[serde(rename = "loginAttemptId")]
     pub login_attempt_id: String,
+    // Also return the JWT in the body, for clients that can't use cookies
+    #[serde(default, rename = "returnToken")]
+    pub return_token: bool,
 }
 
 #[tracing::instrument(name = "Verify 2FA Route Handler", skip_all)]
@@ -23,49 +30,80 @@ pub async fn verify_2fa(
     jar: CookieJar,
     Json(request): Json<Verify2FARequest>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
-    let email = match Email::parse(request.email) {
-        Ok(email) => email,
-        Err(_) => {
-            return (jar, Err(AuthAPIError::InvalidCredentials));
-        }
-    };
-
-    let (login_attempt_id_result, two_fa_code_result) =
-        match state.two_fa_code_store.read().await.get_code(&email).await {
-            Ok(tuple) => tuple,
-            Err(_) => {
-                return (jar, Err(AuthAPIError::IncorrectCredentials));
-            }
-        };
-
-    let login_attempt_id = request.login_attempt_id;
+    match verify_2fa_code(
+        &state,
+        request.email,
+        request.login_attempt_id,
+        request.two_fa_code,
+    )
+    .await
+    {
+        Ok(token) if request.return_token => {
+            let response = Json(TokenResponse {
+                token: token.clone(),
+            });
 
-    if login_attempt_id != *login_attempt_id_result.as_ref().expose_secret() {
-        return (jar, Err(AuthAPIError::IncorrectCredentials));
+            (
+                jar.add(auth::create_auth_cookie(&state.settings.auth_cookie, token)),
+                Ok((StatusCode::OK, response).into_response()),
+            )
+        }
+        Ok(token) => (
+            jar.add(auth::create_auth_cookie(&state.settings.auth_cookie, token)),
+            Ok(StatusCode::OK.into_response()),
+        ),
+        Err(e) => (jar, Err(e)),
     }
+}
 
-    let two_fa_code = request.two_fa_code;
+// Checks the 2FA code of a login attempt and returns the JWT of the user, shared by the
+// HTTP and gRPC APIs.
+#[tracing::instrument(name = "Verify 2FA Code", skip_all)]
+pub async fn verify_2fa_code(
+    state: &AppState,
+    email: Secret<String>,
+    login_attempt_id: String,
+    two_fa_code: String,
+) -> Result<String, AuthAPIError> {
+    let result = check_2fa_code(state, email, login_attempt_id, two_fa_code).await;
+    state.metrics.record_two_fa_verification(&result);
 
-    if two_fa_code != *two_fa_code_result.as_ref().expose_secret() {
-        return (jar, Err(AuthAPIError::IncorrectCredentials));
-    }
+    result
+}
 
-    let auth_cookie = match auth::generate_auth_cookie(&email) {
-        Ok(cookie) => cookie,
-        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
-    };
+async fn check_2fa_code(
+    state: &AppState,
+    email: Secret<String>,
+    login_attempt_id: String,
+    two_fa_code: String,
+) -> Result<String, AuthAPIError> {
+    let email = Email::parse(email).map_err(|_| AuthAPIError::InvalidCredentials)?;
 
-    let jar = jar.add(auth_cookie);
+    // Malformed ones can't match what was sent to the user
+    let login_attempt_id =
+        LoginAttemptId::parse(login_attempt_id).map_err(|_| AuthAPIError::IncorrectCredentials)?;
+    let two_fa_code =
+        TwoFACode::parse(two_fa_code).map_err(|_| AuthAPIError::IncorrectCredentials)?;
 
-    if let Err(e) = state
+    state
         .two_fa_code_store
-        .write()
+        .verify_code(&email, &login_attempt_id, &two_fa_code)
         .await
+        .map_err(|e| match e {
+            TwoFACodeStoreError::LoginAttemptIdNotFound | TwoFACodeStoreError::IncorrectCode => {
+                AuthAPIError::IncorrectCredentials
+            }
+            e => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+
+    let token = auth::generate_auth_token(&email, &state.settings.jwt.secret)
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    state
+        .two_fa_code_store
         .remove_code(&email)
         .await
-    {
-        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
-    }
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
 
-    (jar, Ok(StatusCode::OK.into_response()))
+    Ok(token)
 }
@@ -1,6 +1,14 @@
-use crate::{app_state::AppState, domain::AuthAPIError, utils::auth::validate_token};
-use axum::{extract::State, Json};
-use reqwest::StatusCode;
+use crate::{
+    app_state::AppState,
+    domain::AuthAPIError,
+    utils::{auth::validate_token, extractors::AuthToken},
+};
+use axum::{
+    extract::{rejection::JsonRejection, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
 
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
 pub struct VerifyTokenRequest {
@@ -10,10 +18,27 @@ pub struct VerifyTokenRequest {
 #[tracing::instrument(name = "Verify Token Route Handler", skip_all)]
 pub async fn verify_token(
     State(state): State<AppState>,
-    Json(request): Json<VerifyTokenRequest>,
-) -> Result<StatusCode, AuthAPIError> {
-    match validate_token(&request.token, state.banned_token_store.clone()).await {
-        Ok(_) => Ok(StatusCode::OK),
-        Err(_) => Err(AuthAPIError::InvalidToken),
+    auth_token: Option<AuthToken>,
+    request: Result<Json<VerifyTokenRequest>, JsonRejection>,
+) -> Response {
+    let token = match (request, auth_token) {
+        (Ok(Json(request)), _) => request.token,
+        // Without a JSON body the token can come as a Bearer header or the JWT cookie
+        (Err(JsonRejection::MissingJsonContentType(_)), Some(AuthToken(token))) => token,
+        (Err(JsonRejection::MissingJsonContentType(_)), None) => {
+            return AuthAPIError::MissingToken.into_response()
+        }
+        (Err(rejection), _) => return rejection.into_response(),
+    };
+
+    match validate_token(
+        &token,
+        &state.settings.jwt.secret,
+        state.banned_token_store.clone(),
+    )
+    .await
+    {
+        Ok(_) => StatusCode::OK.into_response(),
+        Err(_) => AuthAPIError::InvalidToken.into_response(),
     }
 }
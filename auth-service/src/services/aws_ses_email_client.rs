@@ -1,8 +1,8 @@
-use crate::domain::{Email, EmailClient};
+use crate::domain::{Email, EmailClient, EmailMessage};
 use aws_config::SdkConfig;
 use aws_sdk_sesv2::types::{Body, Content, Destination, EmailContent, Message};
 use aws_sdk_sesv2::Client;
-use color_eyre::eyre::Result;
+use color_eyre::eyre::{Context, Result};
 use secrecy::ExposeSecret;
 
 pub struct SESEmailClient {
@@ -21,21 +21,19 @@ impl SESEmailClient {
 #[async_trait::async_trait]
 impl EmailClient for SESEmailClient {
     #[tracing::instrument(name = "Sending email", skip_all)]
-    async fn send_email(&self, recipient: &Email, subject: &str, content: &str) -> Result<()> {
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
         let sender = self.sender.as_ref().expose_secret();
         let recipient = recipient.as_ref().expose_secret();
-        let body_text = content;
-        let body_html = format!("<html><body>{}</body></html>", content);
 
         // Create the email content
         let email_content = EmailContent::builder()
             .simple(
                 Message::builder()
-                    .subject(Content::builder().data(subject).build()?)
+                    .subject(Content::builder().data(&message.subject).build()?)
                     .body(
                         Body::builder()
-                            .text(Content::builder().data(body_text).build()?)
-                            .html(Content::builder().data(body_html).build()?)
+                            .text(Content::builder().data(&message.text_body).build()?)
+                            .html(Content::builder().data(&message.html_body).build()?)
                             .build(),
                     )
                     .build(),
@@ -58,6 +56,17 @@ impl EmailClient for SESEmailClient {
 
         Ok(())
     }
+
+    async fn check_connection(&self) -> Result<()> {
+        // Cheapest authenticated call, it also fails when the credentials are wrong
+        self.ses_client
+            .get_account()
+            .send()
+            .await
+            .wrap_err("Failed to reach SES")?;
+
+        Ok(())
+    }
 }
 
 // @todo: add unit tests
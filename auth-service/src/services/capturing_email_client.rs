@@ -0,0 +1,129 @@
+use crate::domain::{Email, EmailClient, EmailMessage};
+use color_eyre::eyre::Result;
+use regex_automata::meta::Regex;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone)]
+pub struct SentEmail {
+    pub recipient: Email,
+    pub message: EmailMessage,
+}
+
+impl SentEmail {
+    // First 6 digit code found in the plain text part, e.g. a 2FA code.
+    pub fn extract_code(&self) -> Option<String> {
+        self.find(r"(?-u:\b)[0-9]{6}(?-u:\b)")
+    }
+
+    // First http(s) link found in the plain text part, e.g. a verification link.
+    pub fn extract_link(&self) -> Option<String> {
+        self.find(r#"https?://[^\s<>"]+"#)
+    }
+
+    fn find(&self, pattern: &str) -> Option<String> {
+        let regex = Regex::new(pattern).expect("Could not build regex pattern");
+        let body = &self.message.text_body;
+
+        regex
+            .find(body.as_str())
+            .map(|found| body[found.range()].to_owned())
+    }
+}
+
+// Keeps every email in memory instead of sending it, so tests can read what a user
+// would have received. Clones share the same mailbox.
+#[derive(Default, Clone)]
+pub struct CapturingEmailClient {
+    sent: Arc<Mutex<Vec<SentEmail>>>,
+}
+
+impl CapturingEmailClient {
+    pub fn sent_emails(&self) -> Vec<SentEmail> {
+        self.sent.lock().expect("Mailbox lock poisoned").clone()
+    }
+
+    pub fn emails_to(&self, recipient: &Email) -> Vec<SentEmail> {
+        self.sent_emails()
+            .into_iter()
+            .filter(|email| email.recipient == *recipient)
+            .collect()
+    }
+
+    pub fn last_email_to(&self, recipient: &Email) -> Option<SentEmail> {
+        self.emails_to(recipient).pop()
+    }
+
+    pub fn clear(&self) {
+        self.sent.lock().expect("Mailbox lock poisoned").clear();
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for CapturingEmailClient {
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
+        self.sent
+            .lock()
+            .expect("Mailbox lock poisoned")
+            .push(SentEmail {
+                recipient: recipient.clone(),
+                message: message.clone(),
+            });
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::Secret;
+
+    fn email(address: &str) -> Email {
+        Email::parse(Secret::new(address.to_owned())).unwrap()
+    }
+
+    fn message(text_body: &str) -> EmailMessage {
+        EmailMessage {
+            subject: "Subject".to_owned(),
+            html_body: format!("<p>{}</p>", text_body),
+            text_body: text_body.to_owned(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_last_email_to_returns_latest_message_for_recipient() {
+        let client = CapturingEmailClient::default();
+        let alice = email("alice@example.com");
+        let bob = email("bob@example.com");
+
+        client.send_email(&alice, &message("first")).await.unwrap();
+        client.send_email(&bob, &message("other")).await.unwrap();
+        client.send_email(&alice, &message("second")).await.unwrap();
+
+        let last = client.last_email_to(&alice).unwrap();
+        assert_eq!(last.message.text_body, "second");
+        assert_eq!(client.emails_to(&alice).len(), 2);
+        assert_eq!(client.sent_emails().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_extracts_code_and_link_from_text_body() {
+        let client = CapturingEmailClient::default();
+        let alice = email("alice@example.com");
+
+        client
+            .send_email(
+                &alice,
+                &message("Your code is 123456. Or open https://example.com/verify?t=abc now"),
+            )
+            .await
+            .unwrap();
+
+        let sent = client.last_email_to(&alice).unwrap();
+        assert_eq!(sent.extract_code(), Some("123456".to_owned()));
+        assert_eq!(
+            sent.extract_link(),
+            Some("https://example.com/verify?t=abc".to_owned())
+        );
+    }
+}
@@ -1,29 +1,61 @@
 use crate::domain::data_stores::{BannedTokenStore, BannedTokenStoreError};
+use chrono::Utc;
 use secrecy::{ExposeSecret, Secret};
-use std::collections::HashSet;
+use std::collections::HashMap;
+use tokio::sync::RwLock;
 
+// Tokens with their expiration, dropped once it has passed
 #[derive(Default, Debug)]
 pub struct HashsetBannedTokenStore {
-    tokens: HashSet<String>,
+    tokens: RwLock<HashMap<String, usize>>,
 }
 
 #[async_trait::async_trait]
 impl BannedTokenStore for HashsetBannedTokenStore {
-    async fn add_token(&mut self, token: Secret<String>) -> Result<(), BannedTokenStoreError> {
-        self.tokens.insert(token.expose_secret().to_owned());
+    async fn add_token(
+        &self,
+        token: Secret<String>,
+        exp: usize,
+    ) -> Result<(), BannedTokenStoreError> {
+        let now = now();
+        if exp <= now {
+            return Err(BannedTokenStoreError::TokenExpired);
+        }
+
+        let mut tokens = self.tokens.write().await;
+        tokens.retain(|_, token_exp| *token_exp > now);
+        tokens.insert(token.expose_secret().to_owned(), exp);
 
         Ok(())
     }
 
     async fn contains_token(&self, token: Secret<String>) -> Result<bool, BannedTokenStoreError> {
-        Ok(self.tokens.contains(token.expose_secret()))
+        Ok(self
+            .tokens
+            .read()
+            .await
+            .get(token.expose_secret())
+            .is_some_and(|exp| *exp > now()))
     }
 
-    async fn empty_store(&mut self) -> Result<(), BannedTokenStoreError> {
-        self.tokens.clear();
+    async fn empty_store(&self) -> Result<(), BannedTokenStoreError> {
+        self.tokens.write().await.clear();
 
         Ok(())
     }
+
+    async fn purge_expired(&self) -> Result<u64, BannedTokenStoreError> {
+        let now = now();
+        let mut tokens = self.tokens.write().await;
+        let count = tokens.len();
+        tokens.retain(|_, token_exp| *token_exp > now);
+
+        Ok((count - tokens.len()) as u64)
+    }
+}
+
+fn now() -> usize {
+    Utc::now().timestamp().try_into().unwrap_or_default()
 }
 
 #[cfg(test)]
@@ -33,23 +65,56 @@ mod tests {
 
     #[tokio::test]
     async fn test_add_token() {
-        let mut store = HashsetBannedTokenStore::default();
+        let store = HashsetBannedTokenStore::default();
         let token = "test_token".to_owned();
 
-        let result = store.add_token(Secret::new(token.clone())).await;
+        let result = store
+            .add_token(Secret::new(token.clone()), now() + 60)
+            .await;
 
         assert!(result.is_ok());
-        assert!(store.tokens.contains(&token));
+        assert!(store.tokens.read().await.contains_key(&token));
     }
 
     #[tokio::test]
     async fn test_contains_token() {
-        let mut store = HashsetBannedTokenStore::default();
+        let store = HashsetBannedTokenStore::default();
         let token = "test_token".to_owned();
-        store.tokens.insert(token.clone());
+        store.tokens.write().await.insert(token.clone(), now() + 60);
 
         let result = store.contains_token(Secret::new(token)).await;
 
         assert!(result.unwrap());
     }
+
+    #[tokio::test]
+    async fn test_add_expired_token() {
+        let store = HashsetBannedTokenStore::default();
+
+        let result = store
+            .add_token(Secret::new("test_token".to_owned()), now())
+            .await;
+
+        assert!(matches!(result, Err(BannedTokenStoreError::TokenExpired)));
+        assert!(store.tokens.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_expired_tokens_are_dropped() {
+        let store = HashsetBannedTokenStore::default();
+        let token = "test_token".to_owned();
+        store.tokens.write().await.insert(token.clone(), now() - 1);
+
+        let result = store.contains_token(Secret::new(token.clone())).await;
+        assert!(!result.unwrap());
+
+        assert_eq!(store.purge_expired().await.unwrap(), 1);
+        store.tokens.write().await.insert(token, now() - 1);
+
+        store
+            .add_token(Secret::new("other_token".to_owned()), now() + 60)
+            .await
+            .unwrap();
+        assert_eq!(store.tokens.read().await.len(), 1);
+    }
 }
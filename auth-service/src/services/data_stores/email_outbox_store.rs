@@ -0,0 +1,206 @@
+use crate::domain::{
+    data_stores::{EmailOutboxStore, EmailOutboxStoreError},
+    OutboxEmail, OutboxEntry, OutboxStatus,
+};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+#[derive(Debug)]
+struct HashmapOutboxEntry {
+    entry: OutboxEntry,
+    status: OutboxStatus,
+    next_attempt_at: DateTime<Utc>,
+}
+
+#[derive(Default, Debug)]
+pub struct HashmapEmailOutboxStore {
+    emails: RwLock<HashMap<Uuid, HashmapOutboxEntry>>,
+}
+
+#[async_trait::async_trait]
+impl EmailOutboxStore for HashmapEmailOutboxStore {
+    async fn enqueue(
+        &self,
+        email: OutboxEmail,
+        available_at: DateTime<Utc>,
+    ) -> Result<Option<Uuid>, EmailOutboxStoreError> {
+        // Checked and inserted under one lock, like the unique index in Postgres
+        let mut emails = self.emails.write().await;
+        if emails
+            .values()
+            .any(|stored| stored.entry.email.idempotency_key == email.idempotency_key)
+        {
+            return Ok(None);
+        }
+
+        let id = Uuid::new_v4();
+        emails.insert(
+            id,
+            HashmapOutboxEntry {
+                entry: OutboxEntry {
+                    id,
+                    attempts: 0,
+                    email,
+                },
+                status: OutboxStatus::Pending,
+                next_attempt_at: available_at,
+            },
+        );
+
+        Ok(Some(id))
+    }
+
+    async fn claim_due(
+        &self,
+        limit: i64,
+        lease_until: DateTime<Utc>,
+    ) -> Result<Vec<OutboxEntry>, EmailOutboxStoreError> {
+        let now = Utc::now();
+        let limit = usize::try_from(limit).unwrap_or_default();
+
+        let claimed = self
+            .emails
+            .write()
+            .await
+            .values_mut()
+            .filter(|stored| {
+                stored.status == OutboxStatus::Pending && stored.next_attempt_at <= now
+            })
+            .take(limit)
+            .map(|stored| {
+                stored.next_attempt_at = lease_until;
+                stored.entry.clone()
+            })
+            .collect();
+
+        Ok(claimed)
+    }
+
+    async fn mark_sent(&self, id: Uuid) -> Result<(), EmailOutboxStoreError> {
+        let mut emails = self.emails.write().await;
+        let stored = emails
+            .get_mut(&id)
+            .ok_or(EmailOutboxStoreError::EmailNotFound)?;
+
+        stored.status = OutboxStatus::Sent;
+
+        Ok(())
+    }
+
+    async fn mark_failed(
+        &self,
+        id: Uuid,
+        error: String,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), EmailOutboxStoreError> {
+        let mut emails = self.emails.write().await;
+        let stored = emails
+            .get_mut(&id)
+            .ok_or(EmailOutboxStoreError::EmailNotFound)?;
+
+        tracing::debug!("Outbox email {} failed: {}", id, error);
+        stored.entry.attempts += 1;
+
+        match retry_at {
+            Some(retry_at) => stored.next_attempt_at = retry_at,
+            None => stored.status = OutboxStatus::Dead,
+        }
+
+        Ok(())
+    }
+
+    async fn get_status(
+        &self,
+        idempotency_key: &str,
+    ) -> Result<Option<OutboxStatus>, EmailOutboxStoreError> {
+        Ok(self
+            .emails
+            .read()
+            .await
+            .values()
+            .find(|stored| stored.entry.email.idempotency_key == idempotency_key)
+            .map(|stored| stored.status))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Email, EmailMessage};
+    use chrono::Duration;
+    use secrecy::Secret;
+
+    fn outbox_email(idempotency_key: &str) -> OutboxEmail {
+        OutboxEmail {
+            idempotency_key: idempotency_key.to_owned(),
+            recipient: Email::parse(Secret::new("testing@email.com".to_owned())).unwrap(),
+            message: EmailMessage {
+                subject: "Subject".to_owned(),
+                html_body: "<p>Body</p>".to_owned(),
+                text_body: "Body".to_owned(),
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_enqueue_is_idempotent() {
+        let store = HashmapEmailOutboxStore::default();
+
+        let first = store
+            .enqueue(outbox_email("key"), Utc::now())
+            .await
+            .unwrap();
+        let second = store
+            .enqueue(outbox_email("key"), Utc::now())
+            .await
+            .unwrap();
+
+        assert!(first.is_some());
+        assert!(second.is_none());
+        assert_eq!(store.emails.read().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_claim_due_hides_claimed_emails_until_lease_ends() {
+        let store = HashmapEmailOutboxStore::default();
+        store
+            .enqueue(outbox_email("due"), Utc::now())
+            .await
+            .unwrap();
+        store
+            .enqueue(outbox_email("later"), Utc::now() + Duration::minutes(5))
+            .await
+            .unwrap();
+
+        let lease_until = Utc::now() + Duration::minutes(1);
+        let claimed = store.claim_due(10, lease_until).await.unwrap();
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(claimed[0].email.idempotency_key, "due");
+
+        let claimed = store.claim_due(10, lease_until).await.unwrap();
+        assert!(claimed.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_mark_failed_without_retry_dead_letters_email() {
+        let store = HashmapEmailOutboxStore::default();
+        let id = store
+            .enqueue(outbox_email("key"), Utc::now())
+            .await
+            .unwrap()
+            .unwrap();
+
+        store
+            .mark_failed(id, "boom".to_owned(), None)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.get_status("key").await.unwrap(),
+            Some(OutboxStatus::Dead)
+        );
+        assert_eq!(store.emails.read().await[&id].entry.attempts, 1);
+    }
+}
@@ -1,11 +1,21 @@
 pub mod banned_token_store;
+pub mod email_outbox_store;
+pub mod postgres_banned_token_store;
+pub mod postgres_email_outbox_store;
+pub mod postgres_two_fa_code_store;
 pub mod redis_banned_token_store;
+pub mod redis_connection;
 pub mod redis_two_fa_code_store;
 pub mod two_fa_token_store;
 pub mod user_store;
 
 pub use banned_token_store::*;
+pub use email_outbox_store::*;
+pub use postgres_banned_token_store::*;
+pub use postgres_email_outbox_store::*;
+pub use postgres_two_fa_code_store::*;
 pub use redis_banned_token_store::*;
+pub use redis_connection::*;
 pub use redis_two_fa_code_store::*;
 pub use two_fa_token_store::*;
 pub use user_store::*;
@@ -0,0 +1,95 @@
+use crate::{
+    domain::data_stores::{BannedTokenStore, BannedTokenStoreError},
+    utils::keyed_hash::KeyedHasher,
+};
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Context};
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+
+// For deployments without Redis. Rows are kept until `ExpiryPurgeWorker` deletes them, expired
+// ones are ignored meanwhile. Like in Redis, tokens are stored as keyed hashes.
+pub struct PostgresBannedTokenStore {
+    pool: PgPool,
+    hasher: KeyedHasher,
+}
+
+impl PostgresBannedTokenStore {
+    pub fn new(pool: PgPool, hasher: KeyedHasher) -> Self {
+        Self { pool, hasher }
+    }
+}
+
+#[async_trait::async_trait]
+impl BannedTokenStore for PostgresBannedTokenStore {
+    #[tracing::instrument(name = "Banning token in PostgreSQL", skip_all)]
+    async fn add_token(
+        &self,
+        token: Secret<String>,
+        exp: usize,
+    ) -> Result<(), BannedTokenStoreError> {
+        let expires_at = i64::try_from(exp)
+            .ok()
+            .and_then(|exp| DateTime::<Utc>::from_timestamp(exp, 0))
+            .ok_or_else(|| eyre!("Invalid token expiration: {}", exp))
+            .map_err(BannedTokenStoreError::UnexpectedError)?;
+        if expires_at <= Utc::now() {
+            return Err(BannedTokenStoreError::TokenExpired);
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO banned_tokens (token_hash, expires_at)
+            VALUES ($1, $2)
+            ON CONFLICT (token_hash) DO UPDATE
+            SET expires_at = GREATEST(banned_tokens.expires_at, EXCLUDED.expires_at)
+            "#,
+        )
+        .bind(self.hasher.hash(token.expose_secret()))
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await
+        .wrap_err("Failed to insert banned token")
+        .map_err(BannedTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Checking banned token in PostgreSQL", skip_all)]
+    async fn contains_token(&self, token: Secret<String>) -> Result<bool, BannedTokenStoreError> {
+        sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM banned_tokens WHERE token_hash = $1 AND expires_at > NOW()
+            )
+            "#,
+        )
+        .bind(self.hasher.hash(token.expose_secret()))
+        .fetch_one(&self.pool)
+        .await
+        .wrap_err("Failed to check if token is banned")
+        .map_err(BannedTokenStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Emptying banned tokens in PostgreSQL", skip_all)]
+    async fn empty_store(&self) -> Result<(), BannedTokenStoreError> {
+        sqlx::query("DELETE FROM banned_tokens")
+            .execute(&self.pool)
+            .await
+            .wrap_err("Failed to delete banned tokens")
+            .map_err(BannedTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Purging expired banned tokens from PostgreSQL", skip_all)]
+    async fn purge_expired(&self) -> Result<u64, BannedTokenStoreError> {
+        let result = sqlx::query("DELETE FROM banned_tokens WHERE expires_at <= NOW()")
+            .execute(&self.pool)
+            .await
+            .wrap_err("Failed to delete expired banned tokens")
+            .map_err(BannedTokenStoreError::UnexpectedError)?;
+
+        Ok(result.rows_affected())
+    }
+}
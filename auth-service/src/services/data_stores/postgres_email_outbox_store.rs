@@ -0,0 +1,200 @@
+use crate::domain::{
+    data_stores::{EmailOutboxStore, EmailOutboxStoreError},
+    Email, EmailMessage, OutboxEmail, OutboxEntry, OutboxStatus,
+};
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::Context;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+pub struct PostgresEmailOutboxStore {
+    pool: PgPool,
+}
+
+impl PostgresEmailOutboxStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[derive(FromRow, Debug)]
+struct PostgresOutboxEntry {
+    id: Uuid,
+    idempotency_key: String,
+    recipient: String,
+    subject: String,
+    html_body: String,
+    text_body: String,
+    attempts: i32,
+}
+
+impl TryFrom<PostgresOutboxEntry> for OutboxEntry {
+    type Error = EmailOutboxStoreError;
+
+    fn try_from(row: PostgresOutboxEntry) -> Result<Self, Self::Error> {
+        let recipient = Email::parse(Secret::new(row.recipient))
+            .map_err(EmailOutboxStoreError::UnexpectedError)?;
+
+        Ok(OutboxEntry {
+            id: row.id,
+            attempts: row.attempts,
+            email: OutboxEmail {
+                idempotency_key: row.idempotency_key,
+                recipient,
+                message: EmailMessage {
+                    subject: row.subject,
+                    html_body: row.html_body,
+                    text_body: row.text_body,
+                },
+            },
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailOutboxStore for PostgresEmailOutboxStore {
+    #[tracing::instrument(name = "Queueing email in PostgreSQL outbox", skip_all)]
+    async fn enqueue(
+        &self,
+        email: OutboxEmail,
+        available_at: DateTime<Utc>,
+    ) -> Result<Option<Uuid>, EmailOutboxStoreError> {
+        let id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO email_outbox (id, idempotency_key, recipient, subject, html_body, text_body, next_attempt_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (idempotency_key) DO NOTHING
+            RETURNING id
+            "#,
+            Uuid::new_v4(),
+            email.idempotency_key,
+            email.recipient.as_ref().expose_secret(),
+            email.message.subject,
+            email.message.html_body,
+            email.message.text_body,
+            available_at
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .wrap_err("Failed to insert email into outbox")
+        .map_err(EmailOutboxStoreError::UnexpectedError)?;
+
+        Ok(id)
+    }
+
+    #[tracing::instrument(name = "Claiming due emails from PostgreSQL outbox", skip_all)]
+    async fn claim_due(
+        &self,
+        limit: i64,
+        lease_until: DateTime<Utc>,
+    ) -> Result<Vec<OutboxEntry>, EmailOutboxStoreError> {
+        // SKIP LOCKED lets several service instances drain the outbox without
+        // claiming the same rows.
+        let rows = sqlx::query_as!(
+            PostgresOutboxEntry,
+            r#"
+            UPDATE email_outbox
+            SET next_attempt_at = $2
+            WHERE id IN (
+                SELECT id FROM email_outbox
+                WHERE status = 'pending' AND next_attempt_at <= NOW()
+                ORDER BY next_attempt_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, idempotency_key, recipient, subject, html_body, text_body, attempts
+            "#,
+            limit,
+            lease_until
+        )
+        .fetch_all(&self.pool)
+        .await
+        .wrap_err("Failed to claim due emails from outbox")
+        .map_err(EmailOutboxStoreError::UnexpectedError)?;
+
+        rows.into_iter().map(OutboxEntry::try_from).collect()
+    }
+
+    #[tracing::instrument(name = "Marking outbox email as sent", skip_all)]
+    async fn mark_sent(&self, id: Uuid) -> Result<(), EmailOutboxStoreError> {
+        // Bodies are cleared once delivered, as they can carry codes and links
+        let result = sqlx::query!(
+            r#"
+            UPDATE email_outbox
+            SET status = $2, sent_at = NOW(), last_error = NULL, html_body = '', text_body = ''
+            WHERE id = $1
+            "#,
+            id,
+            OutboxStatus::Sent.as_str()
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("Failed to mark outbox email as sent")
+        .map_err(EmailOutboxStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(EmailOutboxStoreError::EmailNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Marking outbox email as failed", skip_all)]
+    async fn mark_failed(
+        &self,
+        id: Uuid,
+        error: String,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), EmailOutboxStoreError> {
+        let status = match retry_at {
+            Some(_) => OutboxStatus::Pending,
+            None => OutboxStatus::Dead,
+        };
+
+        // Dead-lettered emails won't be sent anymore, only what explains the failure is kept
+        let result = sqlx::query!(
+            r#"
+            UPDATE email_outbox
+            SET status = $2, attempts = attempts + 1, last_error = $3, next_attempt_at = COALESCE($4, next_attempt_at),
+                html_body = CASE WHEN $4 IS NULL THEN '' ELSE html_body END,
+                text_body = CASE WHEN $4 IS NULL THEN '' ELSE text_body END
+            WHERE id = $1
+            "#,
+            id,
+            status.as_str(),
+            error,
+            retry_at
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("Failed to mark outbox email as failed")
+        .map_err(EmailOutboxStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(EmailOutboxStoreError::EmailNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving outbox email status", skip_all)]
+    async fn get_status(
+        &self,
+        idempotency_key: &str,
+    ) -> Result<Option<OutboxStatus>, EmailOutboxStoreError> {
+        let status = sqlx::query_scalar!(
+            r#"SELECT status FROM email_outbox WHERE idempotency_key = $1"#,
+            idempotency_key
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .wrap_err("Failed to retrieve outbox email status")
+        .map_err(EmailOutboxStoreError::UnexpectedError)?;
+
+        status
+            .map(|status| status.parse())
+            .transpose()
+            .map_err(EmailOutboxStoreError::UnexpectedError)
+    }
+}
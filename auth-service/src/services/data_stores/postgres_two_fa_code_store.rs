@@ -0,0 +1,125 @@
+use crate::{
+    domain::{
+        data_stores::{
+            LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError, TWO_FA_CODE_TTL_SECONDS,
+        },
+        Email,
+    },
+    utils::keyed_hash::KeyedHasher,
+};
+use chrono::{Duration, Utc};
+use color_eyre::eyre::Context;
+use secrecy::ExposeSecret;
+use sqlx::{FromRow, PgPool};
+
+// For deployments without Redis, see `PostgresBannedTokenStore`. Only keyed hashes of the login
+// attempt ids and codes are stored.
+pub struct PostgresTwoFACodeStore {
+    pool: PgPool,
+    hasher: KeyedHasher,
+}
+
+impl PostgresTwoFACodeStore {
+    pub fn new(pool: PgPool, hasher: KeyedHasher) -> Self {
+        Self { pool, hasher }
+    }
+}
+
+#[derive(FromRow, Debug)]
+struct PostgresTwoFACode {
+    login_attempt_id_hash: String,
+    code_hash: String,
+}
+
+#[async_trait::async_trait]
+impl TwoFACodeStore for PostgresTwoFACodeStore {
+    #[tracing::instrument(name = "Adding 2FA code to PostgreSQL", skip_all)]
+    async fn add_code(
+        &self,
+        email: Email,
+        login_attempt_id: LoginAttemptId,
+        code: TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let expires_at = Utc::now() + Duration::seconds(TWO_FA_CODE_TTL_SECONDS);
+
+        // A new login attempt replaces the pending one
+        sqlx::query(
+            r#"
+            INSERT INTO two_fa_codes (email, login_attempt_id_hash, code_hash, expires_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (email) DO UPDATE
+            SET login_attempt_id_hash = EXCLUDED.login_attempt_id_hash,
+                code_hash = EXCLUDED.code_hash,
+                expires_at = EXCLUDED.expires_at
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(self.hasher.hash(login_attempt_id.as_ref().expose_secret()))
+        .bind(self.hasher.hash(code.as_ref().expose_secret()))
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await
+        .wrap_err("Failed to insert 2FA code")
+        .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing 2FA code from PostgreSQL", skip_all)]
+    async fn remove_code(&self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        sqlx::query("DELETE FROM two_fa_codes WHERE email = $1")
+            .bind(email.as_ref().expose_secret())
+            .execute(&self.pool)
+            .await
+            .wrap_err("Failed to delete 2FA code")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Verifying 2FA code in PostgreSQL", skip_all)]
+    async fn verify_code(
+        &self,
+        email: &Email,
+        login_attempt_id: &LoginAttemptId,
+        code: &TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let row = sqlx::query_as::<_, PostgresTwoFACode>(
+            r#"
+            SELECT login_attempt_id_hash, code_hash FROM two_fa_codes
+            WHERE email = $1 AND expires_at > NOW()
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .fetch_optional(&self.pool)
+        .await
+        .wrap_err("Failed to retrieve 2FA code")
+        .map_err(TwoFACodeStoreError::UnexpectedError)?
+        .ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+
+        // Both are compared, whichever is wrong
+        let matches = self.hasher.verify(
+            login_attempt_id.as_ref().expose_secret(),
+            &row.login_attempt_id_hash,
+        ) & self
+            .hasher
+            .verify(code.as_ref().expose_secret(), &row.code_hash);
+
+        if matches {
+            Ok(())
+        } else {
+            Err(TwoFACodeStoreError::IncorrectCode)
+        }
+    }
+
+    #[tracing::instrument(name = "Purging expired 2FA codes from PostgreSQL", skip_all)]
+    async fn purge_expired(&self) -> Result<u64, TwoFACodeStoreError> {
+        let result = sqlx::query("DELETE FROM two_fa_codes WHERE expires_at <= NOW()")
+            .execute(&self.pool)
+            .await
+            .wrap_err("Failed to delete expired 2FA codes")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -1,23 +1,86 @@
-use chrono::Duration;
 use color_eyre::eyre::Context;
-use redis::{Commands, Connection};
+use redis::{AsyncCommands, Script};
 use secrecy::ExposeSecret;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use tokio::sync::RwLock;
-
-use crate::domain::{
-    data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
-    Email,
+use subtle::ConstantTimeEq;
+
+use super::RedisConnection;
+use crate::{
+    domain::{
+        data_stores::{
+            LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError, TWO_FA_CODE_TTL_SECONDS,
+        },
+        Email,
+    },
+    utils::keyed_hash::KeyedHasher,
 };
 
+// Shares the multiplexed connection of `RedisBannedTokenStore`. Only keyed hashes of the login
+// attempt ids and codes are stored.
 pub struct RedisTwoFACodeStore {
-    conn: Arc<RwLock<Connection>>,
+    conn: RedisConnection,
+    // Namespace of the deployment, see `RedisSettings::key_prefix`
+    key_prefix: String,
+    hasher: KeyedHasher,
 }
 
 impl RedisTwoFACodeStore {
-    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
-        Self { conn }
+    pub fn new(conn: RedisConnection, key_prefix: String, hasher: KeyedHasher) -> Self {
+        Self {
+            conn,
+            key_prefix,
+            hasher,
+        }
+    }
+
+    // Hashes the codes stored in plain text by earlier versions, keeping their expiry. Codes
+    // that aren't migrated, e.g. on a cluster, are still verified until they expire.
+    #[tracing::instrument(name = "RedisTwoFACodeStore:: Migrate Legacy Codes", skip_all)]
+    pub async fn migrate_legacy_codes(&self) -> color_eyre::eyre::Result<usize> {
+        let keys = self
+            .conn
+            .scan_prefixed(&format!("{}{}", self.key_prefix, TWO_FA_CODE_PREFIX))
+            .await
+            .wrap_err("Failed to list the 2FA codes in Redis")?;
+
+        // Only replaces the value it read, in case the user logged in again meanwhile
+        let replace = Script::new(
+            r"if redis.call('GET', KEYS[1]) == ARGV[1] then
+                return redis.call('SET', KEYS[1], ARGV[2], 'KEEPTTL')
+            end",
+        );
+
+        let mut migrated = 0;
+        for key in keys {
+            let mut conn = self.conn.clone();
+            let Some(value) = conn
+                .get::<_, Option<String>>(&key)
+                .await
+                .wrap_err("Failed to get 2FA code from Redis")?
+            else {
+                continue;
+            };
+            let Ok(StoredCode::Legacy(login_attempt_id, code)) = serde_json::from_str(&value)
+            else {
+                continue;
+            };
+
+            let hashed = serde_json::to_string(&StoredCode::Hashed {
+                login_attempt_id_hash: self.hasher.hash(&login_attempt_id),
+                code_hash: self.hasher.hash(&code),
+            })
+            .wrap_err("Failed to serialize 2FA code hashes")?;
+            replace
+                .key(&key)
+                .arg(value)
+                .arg(hashed)
+                .invoke_async::<_, ()>(&mut conn)
+                .await
+                .wrap_err("Failed to replace 2FA code in Redis")?;
+            migrated += 1;
+        }
+
+        Ok(migrated)
     }
 }
 
@@ -25,31 +88,31 @@ impl RedisTwoFACodeStore {
 impl TwoFACodeStore for RedisTwoFACodeStore {
     #[tracing::instrument(name = "RedisTwoFACodeStore:: Add Code", skip_all)]
     async fn add_code(
-        &mut self,
+        &self,
         email: Email,
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError> {
-        let key = get_key(&email);
+        let key = get_key(&self.key_prefix, &email);
 
-        let data = TwoFATuple(
-            login_attempt_id.as_ref().expose_secret().to_string(),
-            code.as_ref().expose_secret().to_string(),
-        );
+        let data = StoredCode::Hashed {
+            login_attempt_id_hash: self.hasher.hash(login_attempt_id.as_ref().expose_secret()),
+            code_hash: self.hasher.hash(code.as_ref().expose_secret()),
+        };
         let serialized_data = serde_json::to_string(&data)
-            .wrap_err("Failed to serialize 2Fa tuple")
+            .wrap_err("Failed to serialize 2FA code hashes")
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
-        let ttl_in_seconds: u64 = TEN_MINUTES_IN_SECONDS
+        let ttl_in_seconds: u64 = TWO_FA_CODE_TTL_SECONDS
             .try_into()
-            .wrap_err("Failed to cast 10 minutes representation from i64 to u64")
+            .wrap_err("Failed to cast TWO_FA_CODE_TTL_SECONDS to u64")
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
         let _: () = self
             .conn
-            .write()
-            .await
+            .clone()
             .set_ex::<String, String, ()>(key, serialized_data, ttl_in_seconds)
+            .await
             .wrap_err("Failed to set 2FA code in Redis")
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
@@ -57,56 +120,112 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
     }
 
     #[tracing::instrument(name = "RedisTwoFACodeStore:: Remove Code", skip_all)]
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
-        let key = get_key(email);
+    async fn remove_code(&self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        let key = get_key(&self.key_prefix, email);
         let _: () = self
             .conn
-            .write()
-            .await
+            .clone()
             .del::<String, ()>(key)
+            .await
             .wrap_err("Failed to delete 2FA code from Redis")
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
         Ok(())
     }
 
-    #[tracing::instrument(name = "RedisTwoFACodeStore:: Get Code", skip_all)]
-    async fn get_code(
+    #[tracing::instrument(name = "RedisTwoFACodeStore:: Verify Code", skip_all)]
+    async fn verify_code(
         &self,
         email: &Email,
-    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
-        let key = get_key(email);
+        login_attempt_id: &LoginAttemptId,
+        code: &TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let key = get_key(&self.key_prefix, email);
 
-        let two_fa_tuple = match self
+        let Some(value) = self
             .conn
-            .write()
+            .clone()
+            .get::<String, Option<String>>(key)
             .await
-            .get::<String, String>(key)
-            .map_err(|_| TwoFACodeStoreError::LoginAttemptIdNotFound)
-        {
-            Ok(value) => serde_json::from_str::<TwoFATuple>(&value)
-                .wrap_err("Failed to deserialize 2FA tuple")
-                .map_err(|_| TwoFACodeStoreError::LoginAttemptIdNotFound)?,
-            Err(_) => return Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
+            .wrap_err("Failed to get 2FA code from Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?
+        else {
+            return Err(TwoFACodeStoreError::LoginAttemptIdNotFound);
         };
-
-        let TwoFATuple(login_attempt_id, two_fa_code) = two_fa_tuple;
-
-        let login_attempt_id = LoginAttemptId::parse(login_attempt_id)
+        let stored = serde_json::from_str::<StoredCode>(&value)
+            .wrap_err("Failed to deserialize 2FA code")
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
-        let two_fa_code =
-            TwoFACode::parse(two_fa_code).map_err(TwoFACodeStoreError::UnexpectedError)?;
 
-        Ok((login_attempt_id, two_fa_code))
+        let login_attempt_id = login_attempt_id.as_ref().expose_secret();
+        let code = code.as_ref().expose_secret();
+        // Both are compared, whichever is wrong
+        let matches = match stored {
+            StoredCode::Hashed {
+                login_attempt_id_hash,
+                code_hash,
+            } => {
+                self.hasher.verify(login_attempt_id, &login_attempt_id_hash)
+                    & self.hasher.verify(code, &code_hash)
+            }
+            StoredCode::Legacy(expected_login_attempt_id, expected_code) => bool::from(
+                login_attempt_id
+                    .as_bytes()
+                    .ct_eq(expected_login_attempt_id.as_bytes())
+                    & code.as_bytes().ct_eq(expected_code.as_bytes()),
+            ),
+        };
+
+        if matches {
+            Ok(())
+        } else {
+            Err(TwoFACodeStoreError::IncorrectCode)
+        }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct TwoFATuple(pub String, pub String);
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum StoredCode {
+    Hashed {
+        login_attempt_id_hash: String,
+        code_hash: String,
+    },
+    // Plain text login attempt id and code, written by earlier versions
+    Legacy(String, String),
+}
 
-const TEN_MINUTES_IN_SECONDS: i64 = Duration::minutes(10).num_seconds();
 const TWO_FA_CODE_PREFIX: &str = "two_fa_code:";
 
-fn get_key(email: &Email) -> String {
-    format!("{}{}", TWO_FA_CODE_PREFIX, email.as_ref().expose_secret())
+fn get_key(key_prefix: &str, email: &Email) -> String {
+    format!(
+        "{}{}{}",
+        key_prefix,
+        TWO_FA_CODE_PREFIX,
+        email.as_ref().expose_secret()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_hashed_and_legacy_codes() {
+        let hashed = serde_json::to_string(&StoredCode::Hashed {
+            login_attempt_id_hash: "a".to_owned(),
+            code_hash: "b".to_owned(),
+        })
+        .unwrap();
+        assert!(matches!(
+            serde_json::from_str(&hashed).unwrap(),
+            StoredCode::Hashed { login_attempt_id_hash, code_hash }
+                if login_attempt_id_hash == "a" && code_hash == "b"
+        ));
+
+        // As written by earlier versions
+        assert!(matches!(
+            serde_json::from_str(r#"["id","123456"]"#).unwrap(),
+            StoredCode::Legacy(id, code) if id == "id" && code == "123456"
+        ));
+    }
 }
@@ -1,52 +1,72 @@
 use color_eyre::eyre::{eyre, Result};
-use secrecy::ExposeSecret;
 
 use crate::domain::{
     data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
     Email,
 };
+use secrecy::{ExposeSecret, Secret};
 use std::collections::HashMap;
+use subtle::{Choice, ConstantTimeEq};
+use tokio::sync::RwLock;
 
 #[derive(Default, Debug)]
 pub struct HashmapTwoFACodeStore {
-    codes: HashMap<Email, (LoginAttemptId, TwoFACode)>,
+    codes: RwLock<HashMap<Email, (LoginAttemptId, TwoFACode)>>,
 }
 
 #[async_trait::async_trait]
 impl TwoFACodeStore for HashmapTwoFACodeStore {
     async fn add_code(
-        &mut self,
+        &self,
         email: Email,
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError> {
         let value = (login_attempt_id, code);
-        self.codes.insert(email, value);
+        self.codes.write().await.insert(email, value);
 
         Ok(())
     }
 
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
-        match self.codes.remove(email) {
+    async fn remove_code(&self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        match self.codes.write().await.remove(email) {
             Some(_) => Ok(()),
-            None => Err(TwoFACodeStoreError::UnexpectedError(eyre!(format!(
-                "Failed to remove email: {} from the 2FA Code Store",
-                &email.as_ref().expose_secret()
-            )))),
+            None => Err(TwoFACodeStoreError::UnexpectedError(eyre!(
+                "Failed to remove the 2FA code of an unknown email from the 2FA Code Store"
+            ))),
         }
     }
 
-    async fn get_code(
+    async fn verify_code(
         &self,
         email: &Email,
-    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
-        match self.codes.get(email) {
-            Some(code) => Ok(code.clone()),
-            None => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
+        login_attempt_id: &LoginAttemptId,
+        code: &TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let codes = self.codes.read().await;
+        let Some((expected_login_attempt_id, expected_code)) = codes.get(email) else {
+            return Err(TwoFACodeStoreError::LoginAttemptIdNotFound);
+        };
+
+        // Both are compared, whichever is wrong
+        let matches = ct_eq(
+            login_attempt_id.as_ref(),
+            expected_login_attempt_id.as_ref(),
+        ) & ct_eq(code.as_ref(), expected_code.as_ref());
+        if bool::from(matches) {
+            Ok(())
+        } else {
+            Err(TwoFACodeStoreError::IncorrectCode)
         }
     }
 }
 
+fn ct_eq(a: &Secret<String>, b: &Secret<String>) -> Choice {
+    a.expose_secret()
+        .as_bytes()
+        .ct_eq(b.expose_secret().as_bytes())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -60,7 +80,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_add_2fa_code() {
-        let mut two_fa_code_store = HashmapTwoFACodeStore::default();
+        let two_fa_code_store = HashmapTwoFACodeStore::default();
         let email = Email::parse(Secret::new(DEFAULT_EMAIL.to_string())).unwrap();
         let login_attempt_id = LoginAttemptId::default();
         let two_fa_code = TwoFACode::default();
@@ -73,7 +93,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_remove_2fa_code() {
-        let mut two_fa_code_store = HashmapTwoFACodeStore::default();
+        let two_fa_code_store = HashmapTwoFACodeStore::default();
         let email = Email::parse(Secret::new(DEFAULT_EMAIL.to_string())).unwrap();
         let login_attempt_id = LoginAttemptId::default();
         let code = TwoFACode::default();
@@ -89,27 +109,44 @@ mod tests {
         let result = two_fa_code_store.remove_code(&email).await;
         assert_eq!(
             result,
-            Err(TwoFACodeStoreError::UnexpectedError(eyre!(format!(
-                "Failed to remove email: {} from the 2FA Code Store",
-                &email.as_ref().expose_secret()
-            ))))
+            Err(TwoFACodeStoreError::UnexpectedError(eyre!(
+                "Failed to remove the 2FA code of an unknown email from the 2FA Code Store"
+            )))
         );
     }
 
     #[tokio::test]
-    async fn test_get_2fa_code() {
-        let mut two_fa_code_store = HashmapTwoFACodeStore::default();
+    async fn test_verify_2fa_code() {
+        let two_fa_code_store = HashmapTwoFACodeStore::default();
         let email = Email::parse(Secret::new(DEFAULT_EMAIL.to_string())).unwrap();
         let login_attempt_id = LoginAttemptId::default();
         let code = TwoFACode::default();
 
+        let result = two_fa_code_store
+            .verify_code(&email, &login_attempt_id, &code)
+            .await;
+        assert_eq!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+
         let result = two_fa_code_store
             .add_code(email.clone(), login_attempt_id.clone(), code.clone())
             .await;
         assert!(result.is_ok());
 
-        let result = two_fa_code_store.get_code(&email).await.unwrap();
-        assert_eq!(result.0, login_attempt_id);
-        assert_eq!(result.1, code);
+        let result = two_fa_code_store
+            .verify_code(&email, &login_attempt_id, &code)
+            .await;
+        assert!(result.is_ok());
+
+        let result = two_fa_code_store
+            .verify_code(&email, &LoginAttemptId::default(), &code)
+            .await;
+        assert_eq!(result, Err(TwoFACodeStoreError::IncorrectCode));
+
+        // Defaults never start with a 0
+        let other_code = TwoFACode::parse("012345".to_owned()).unwrap();
+        let result = two_fa_code_store
+            .verify_code(&email, &login_attempt_id, &other_code)
+            .await;
+        assert_eq!(result, Err(TwoFACodeStoreError::IncorrectCode));
     }
 }
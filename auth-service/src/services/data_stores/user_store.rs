@@ -3,34 +3,34 @@ use crate::domain::{
     Email, Password, User,
 };
 use std::collections::HashMap;
+use tokio::sync::RwLock;
 
+// The lock is only held for the map operation, never across another await
 #[derive(Default, Debug)]
 pub struct HashmapUserStore {
-    users: HashMap<Email, User>,
+    users: RwLock<HashMap<Email, User>>,
 }
 
 #[async_trait::async_trait]
 impl UserStore for HashmapUserStore {
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
-        if self.users.contains_key(&user.email) {
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError> {
+        let mut users = self.users.write().await;
+        if users.contains_key(&user.email) {
             return Err(UserStoreError::UserAlreadyExists);
         }
-        self.users.insert(user.email.clone(), user);
+        users.insert(user.email.clone(), user);
         Ok(())
     }
 
-    async fn delete_user(&mut self, user: User) -> Result<(), UserStoreError> {
-        if self.users.contains_key(&user.email) {
-            self.users.remove(&user.email);
-
-            return Ok(());
+    async fn delete_user(&self, user: User) -> Result<(), UserStoreError> {
+        match self.users.write().await.remove(&user.email) {
+            Some(_) => Ok(()),
+            None => Err(UserStoreError::UserNotFound),
         }
-
-        Err(UserStoreError::UserNotFound)
     }
 
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
-        match self.users.get(email) {
+        match self.users.read().await.get(email) {
             Some(user) => Ok(user.clone()),
             None => Err(UserStoreError::UserNotFound),
         }
@@ -41,7 +41,7 @@ impl UserStore for HashmapUserStore {
         email: &Email,
         password: &Password,
     ) -> Result<(), UserStoreError> {
-        match self.users.get(email) {
+        match self.users.read().await.get(email) {
             Some(user) => {
                 if user.password.eq(password) {
                     Ok(())
@@ -64,7 +64,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_add_user() {
-        let mut user_store = HashmapUserStore::default();
+        let user_store = HashmapUserStore::default();
         let user = User {
             email: Email::parse(Secret::new(DEFAULT_EMAIL.to_owned())).unwrap(),
             password: Password::parse(Secret::new(DEFAULT_PASSWORD.to_owned())).unwrap(),
@@ -80,9 +80,35 @@ mod tests {
         assert_eq!(result, Err(UserStoreError::UserAlreadyExists));
     }
 
+    #[tokio::test]
+    async fn test_concurrent_add_user_adds_once() {
+        let user_store = std::sync::Arc::new(HashmapUserStore::default());
+        let user = User {
+            email: Email::parse(Secret::new(DEFAULT_EMAIL.to_owned())).unwrap(),
+            password: Password::parse(Secret::new(DEFAULT_PASSWORD.to_owned())).unwrap(),
+            requires_2fa: false,
+        };
+
+        let tasks: Vec<_> = (0..10)
+            .map(|_| {
+                let (user_store, user) = (user_store.clone(), user.clone());
+                tokio::spawn(async move { user_store.add_user(user).await })
+            })
+            .collect();
+
+        let mut added = 0;
+        for task in tasks {
+            match task.await.unwrap() {
+                Ok(()) => added += 1,
+                Err(e) => assert_eq!(e, UserStoreError::UserAlreadyExists),
+            }
+        }
+        assert_eq!(added, 1);
+    }
+
     #[tokio::test]
     async fn test_get_user() {
-        let mut user_store = HashmapUserStore::default();
+        let user_store = HashmapUserStore::default();
         let email = Email::parse(Secret::new(DEFAULT_EMAIL.to_owned())).unwrap();
 
         let user = User {
@@ -92,7 +118,11 @@ mod tests {
         };
 
         // Test getting a user that exists
-        user_store.users.insert(email.clone(), user.clone());
+        user_store
+            .users
+            .write()
+            .await
+            .insert(email.clone(), user.clone());
         let result = user_store.get_user(&email).await;
         assert_eq!(result, Ok(user));
 
@@ -106,7 +136,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_validate_user() {
-        let mut user_store = HashmapUserStore::default();
+        let user_store = HashmapUserStore::default();
         let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
         let password = Password::parse(Secret::new("abcDEF123".to_owned())).unwrap();
 
@@ -117,7 +147,11 @@ mod tests {
         };
 
         // Test validating a user that exists with correct password
-        user_store.users.insert(email.clone(), user.clone());
+        user_store
+            .users
+            .write()
+            .await
+            .insert(email.clone(), user.clone());
         let result = user_store.validate_user(&email, &password).await;
         assert_eq!(result, Ok(()));
 
@@ -0,0 +1,417 @@
+use crate::{
+    app_state::{EmailClientType, EmailOutboxStoreType},
+    domain::{EmailDelivery, OutboxEmail, OutboxEntry},
+    utils::constants::email_outbox::{
+        BASE_BACKOFF_SECONDS, BATCH_SIZE, LEASE_SECONDS, MAX_ATTEMPTS, MAX_BACKOFF_SECONDS,
+        POLL_INTERVAL_MILLISECONDS, SYNC_SEND_TIMEOUT_SECONDS,
+    },
+    utils::metrics::Metrics,
+};
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::{Context, Result};
+use std::{sync::Arc, time::Duration as StdDuration};
+use tokio_util::sync::CancellationToken;
+
+// Hands an email over for delivery. The email is always written to the outbox first,
+// so it survives a slow or failing provider and is retried by `EmailOutboxWorker`.
+#[tracing::instrument(name = "Delivering email", skip_all)]
+pub async fn deliver_email(
+    outbox: &EmailOutboxStoreType,
+    email_client: &EmailClientType,
+    metrics: &Metrics,
+    email: OutboxEmail,
+    delivery: EmailDelivery,
+) -> Result<()> {
+    match delivery {
+        EmailDelivery::Outbox => {
+            outbox
+                .enqueue(email, Utc::now())
+                .await
+                .wrap_err("Failed to queue email in outbox")?;
+        }
+        EmailDelivery::SyncWithFallback => {
+            // Keep the worker away from the email while we try to send it ourselves
+            let lease_until = Utc::now() + Duration::seconds(LEASE_SECONDS);
+            let id = match outbox
+                .enqueue(email.clone(), lease_until)
+                .await
+                .wrap_err("Failed to queue email in outbox")?
+            {
+                Some(id) => id,
+                None => {
+                    tracing::debug!("Email {} was already queued", email.idempotency_key);
+                    return Ok(());
+                }
+            };
+
+            let send_result = tokio::time::timeout(
+                StdDuration::from_secs(SYNC_SEND_TIMEOUT_SECONDS),
+                email_client.send_email(&email.recipient, &email.message),
+            )
+            .await;
+
+            if !matches!(send_result, Ok(Ok(()))) {
+                metrics.record_email_send_failure();
+            }
+
+            match send_result {
+                Ok(Ok(())) => outbox.mark_sent(id).await?,
+                Ok(Err(e)) => {
+                    tracing::warn!("Sending email failed, leaving it to the outbox worker");
+                    outbox
+                        .mark_failed(id, format!("{:#}", e), next_retry_at(1))
+                        .await?
+                }
+                Err(_) => {
+                    tracing::warn!("Sending email timed out, leaving it to the outbox worker");
+                    outbox
+                        .mark_failed(id, "Timed out".to_owned(), next_retry_at(1))
+                        .await?
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Exponential backoff: 5s, 10s, 20s... capped at one hour.
+pub fn backoff_delay(failures: i32) -> Duration {
+    let exponent = u32::try_from(failures.saturating_sub(1)).unwrap_or_default();
+    let seconds = 2_i64
+        .checked_pow(exponent)
+        .and_then(|factor| factor.checked_mul(BASE_BACKOFF_SECONDS))
+        .map_or(MAX_BACKOFF_SECONDS, |seconds| {
+            seconds.min(MAX_BACKOFF_SECONDS)
+        });
+
+    Duration::seconds(seconds)
+}
+
+// When to retry after `failures` failed attempts, or `None` once the email should be dead-lettered.
+pub fn next_retry_at(failures: i32) -> Option<DateTime<Utc>> {
+    if failures >= MAX_ATTEMPTS {
+        return None;
+    }
+
+    Some(Utc::now() + backoff_delay(failures))
+}
+
+// Background task draining the outbox through the configured `EmailClient`.
+pub struct EmailOutboxWorker {
+    outbox: EmailOutboxStoreType,
+    email_client: EmailClientType,
+    metrics: Arc<Metrics>,
+}
+
+impl EmailOutboxWorker {
+    pub fn new(
+        outbox: EmailOutboxStoreType,
+        email_client: EmailClientType,
+        metrics: Arc<Metrics>,
+    ) -> Self {
+        Self {
+            outbox,
+            email_client,
+            metrics,
+        }
+    }
+
+    // Polls until `stop` is cancelled. A batch that was already claimed is finished first, so
+    // its emails aren't left leased until the lease runs out.
+    pub async fn run(self, stop: CancellationToken) {
+        let mut interval =
+            tokio::time::interval(StdDuration::from_millis(POLL_INTERVAL_MILLISECONDS));
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = stop.cancelled() => break,
+            }
+
+            if let Err(e) = self.process_due_emails().await {
+                tracing::error!("Failed to process email outbox: {:?}", e);
+            }
+        }
+
+        tracing::info!("Email outbox worker stopped");
+    }
+
+    // Sends every email that is due, returning how many were processed. An email whose outcome
+    // can't be recorded doesn't hold up the rest of the batch, it stays leased and is retried
+    // once the lease runs out.
+    pub async fn process_due_emails(&self) -> Result<usize> {
+        let lease_until = Utc::now() + Duration::seconds(LEASE_SECONDS);
+        let entries = self
+            .outbox
+            .claim_due(BATCH_SIZE, lease_until)
+            .await
+            .wrap_err("Failed to claim emails from outbox")?;
+        let count = entries.len();
+
+        for entry in entries {
+            let idempotency_key = entry.email.idempotency_key.clone();
+            if let Err(e) = self.deliver(entry).await {
+                tracing::error!("Failed to deliver email {}: {:?}", idempotency_key, e);
+            }
+        }
+
+        Ok(count)
+    }
+
+    #[tracing::instrument(name = "Delivering outbox email", skip_all)]
+    async fn deliver(&self, entry: OutboxEntry) -> Result<()> {
+        let result = self
+            .email_client
+            .send_email(&entry.email.recipient, &entry.email.message)
+            .await;
+
+        match result {
+            Ok(()) => self.outbox.mark_sent(entry.id).await?,
+            Err(e) => {
+                self.metrics.record_email_send_failure();
+                let failures = entry.attempts + 1;
+                let retry_at = next_retry_at(failures);
+
+                if retry_at.is_none() {
+                    tracing::error!(
+                        "Giving up on email {} after {} attempts",
+                        entry.email.idempotency_key,
+                        failures
+                    );
+                }
+
+                self.outbox
+                    .mark_failed(entry.id, format!("{:#}", e), retry_at)
+                    .await?
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::{
+            data_stores::{EmailOutboxStore, EmailOutboxStoreError},
+            Email, EmailClient, EmailMessage, OutboxStatus,
+        },
+        services::{data_stores::HashmapEmailOutboxStore, mock_email_client::MockEmailClient},
+    };
+    use color_eyre::eyre::eyre;
+    use prometheus::Registry;
+    use secrecy::Secret;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use uuid::Uuid;
+
+    struct FailingEmailClient;
+
+    #[async_trait::async_trait]
+    impl EmailClient for FailingEmailClient {
+        async fn send_email(&self, _recipient: &Email, _message: &EmailMessage) -> Result<()> {
+            Err(eyre!("Email provider is down"))
+        }
+    }
+
+    // Fails to record the first email sent
+    #[derive(Default)]
+    struct FlakyEmailOutboxStore {
+        inner: HashmapEmailOutboxStore,
+        failed: AtomicBool,
+    }
+
+    #[async_trait::async_trait]
+    impl EmailOutboxStore for FlakyEmailOutboxStore {
+        async fn enqueue(
+            &self,
+            email: OutboxEmail,
+            available_at: DateTime<Utc>,
+        ) -> Result<Option<Uuid>, EmailOutboxStoreError> {
+            self.inner.enqueue(email, available_at).await
+        }
+
+        async fn claim_due(
+            &self,
+            limit: i64,
+            lease_until: DateTime<Utc>,
+        ) -> Result<Vec<OutboxEntry>, EmailOutboxStoreError> {
+            self.inner.claim_due(limit, lease_until).await
+        }
+
+        async fn mark_sent(&self, id: Uuid) -> Result<(), EmailOutboxStoreError> {
+            if !self.failed.swap(true, Ordering::SeqCst) {
+                return Err(EmailOutboxStoreError::UnexpectedError(eyre!(
+                    "Database is down"
+                )));
+            }
+            self.inner.mark_sent(id).await
+        }
+
+        async fn mark_failed(
+            &self,
+            id: Uuid,
+            error: String,
+            retry_at: Option<DateTime<Utc>>,
+        ) -> Result<(), EmailOutboxStoreError> {
+            self.inner.mark_failed(id, error, retry_at).await
+        }
+
+        async fn get_status(
+            &self,
+            idempotency_key: &str,
+        ) -> Result<Option<OutboxStatus>, EmailOutboxStoreError> {
+            self.inner.get_status(idempotency_key).await
+        }
+    }
+
+    fn metrics() -> Arc<Metrics> {
+        Arc::new(Metrics::new(Registry::new()).unwrap())
+    }
+
+    fn outbox_email() -> OutboxEmail {
+        OutboxEmail {
+            idempotency_key: "two_fa_code:test".to_owned(),
+            recipient: Email::parse(Secret::new("testing@email.com".to_owned())).unwrap(),
+            message: EmailMessage {
+                subject: "Subject".to_owned(),
+                html_body: "<p>Body</p>".to_owned(),
+                text_body: "Body".to_owned(),
+            },
+        }
+    }
+
+    #[test]
+    fn backoff_grows_exponentially_and_is_capped() {
+        assert_eq!(backoff_delay(1), Duration::seconds(BASE_BACKOFF_SECONDS));
+        assert_eq!(
+            backoff_delay(2),
+            Duration::seconds(BASE_BACKOFF_SECONDS * 2)
+        );
+        assert_eq!(
+            backoff_delay(3),
+            Duration::seconds(BASE_BACKOFF_SECONDS * 4)
+        );
+        assert_eq!(backoff_delay(100), Duration::seconds(MAX_BACKOFF_SECONDS));
+        assert!(next_retry_at(MAX_ATTEMPTS).is_none());
+    }
+
+    #[tokio::test]
+    async fn sync_delivery_marks_email_as_sent() {
+        let outbox: EmailOutboxStoreType = Arc::new(HashmapEmailOutboxStore::default());
+        let email_client: EmailClientType = Arc::new(MockEmailClient);
+
+        deliver_email(
+            &outbox,
+            &email_client,
+            &metrics(),
+            outbox_email(),
+            EmailDelivery::SyncWithFallback,
+        )
+        .await
+        .unwrap();
+
+        let status = outbox.get_status("two_fa_code:test").await;
+        assert_eq!(status.unwrap(), Some(OutboxStatus::Sent));
+    }
+
+    #[tokio::test]
+    async fn sync_delivery_falls_back_to_outbox_when_provider_fails() {
+        let outbox: EmailOutboxStoreType = Arc::new(HashmapEmailOutboxStore::default());
+        let email_client: EmailClientType = Arc::new(FailingEmailClient);
+        let metrics = metrics();
+
+        let result = deliver_email(
+            &outbox,
+            &email_client,
+            &metrics,
+            outbox_email(),
+            EmailDelivery::SyncWithFallback,
+        )
+        .await;
+
+        assert!(result.is_ok());
+        assert!(metrics
+            .render()
+            .unwrap()
+            .contains("auth_email_send_failures_total 1"));
+
+        let status = outbox.get_status("two_fa_code:test").await;
+        assert_eq!(status.unwrap(), Some(OutboxStatus::Pending));
+    }
+
+    #[tokio::test]
+    async fn worker_stops_when_cancelled() {
+        let outbox: EmailOutboxStoreType = Arc::new(HashmapEmailOutboxStore::default());
+        let worker = EmailOutboxWorker::new(outbox, Arc::new(MockEmailClient), metrics());
+        let stop = CancellationToken::new();
+
+        let handle = tokio::spawn(worker.run(stop.clone()));
+        stop.cancel();
+
+        tokio::time::timeout(StdDuration::from_secs(1), handle)
+            .await
+            .expect("The worker didn't stop")
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn worker_retries_and_dead_letters_email_after_max_attempts() {
+        let outbox: EmailOutboxStoreType = Arc::new(HashmapEmailOutboxStore::default());
+        let worker =
+            EmailOutboxWorker::new(outbox.clone(), Arc::new(FailingEmailClient), metrics());
+
+        let id = outbox
+            .enqueue(outbox_email(), Utc::now())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(worker.process_due_emails().await.unwrap(), 1);
+        let status = outbox.get_status("two_fa_code:test").await;
+        assert_eq!(status.unwrap(), Some(OutboxStatus::Pending));
+
+        // The retry is scheduled in the future, so there is nothing due right now
+        assert_eq!(worker.process_due_emails().await.unwrap(), 0);
+
+        // Fast-forward through the remaining retries, making the email due straight away
+        for _ in 1..MAX_ATTEMPTS - 1 {
+            outbox
+                .mark_failed(id, "boom".to_owned(), Some(Utc::now()))
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(worker.process_due_emails().await.unwrap(), 1);
+        let status = outbox.get_status("two_fa_code:test").await;
+        assert_eq!(status.unwrap(), Some(OutboxStatus::Dead));
+    }
+
+    #[tokio::test]
+    async fn worker_delivers_the_rest_of_the_batch_when_one_email_fails() {
+        let outbox: EmailOutboxStoreType = Arc::new(FlakyEmailOutboxStore::default());
+        let worker = EmailOutboxWorker::new(outbox.clone(), Arc::new(MockEmailClient), metrics());
+        let keys = ["two_fa_code:first", "two_fa_code:second"];
+        for key in keys {
+            let email = OutboxEmail {
+                idempotency_key: key.to_owned(),
+                ..outbox_email()
+            };
+            outbox.enqueue(email, Utc::now()).await.unwrap();
+        }
+
+        assert_eq!(worker.process_due_emails().await.unwrap(), 2);
+
+        let mut statuses = Vec::new();
+        for key in keys {
+            statuses.push(outbox.get_status(key).await.unwrap());
+        }
+        statuses.sort_by_key(|status| status == &Some(OutboxStatus::Sent));
+        assert_eq!(
+            statuses,
+            [Some(OutboxStatus::Pending), Some(OutboxStatus::Sent)]
+        );
+    }
+}
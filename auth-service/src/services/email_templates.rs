@@ -0,0 +1,247 @@
+use crate::domain::{EmailMessage, Locale, TwoFACode};
+use askama::Template;
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{Context, Result};
+use secrecy::ExposeSecret;
+
+/// Every kind of email we send implements this trait, so callers only pick the
+/// typed template and the locale and never deal with subjects or markup.
+pub trait EmailTemplate {
+    fn render(&self, locale: Locale) -> Result<EmailMessage>;
+}
+
+pub struct TwoFACodeEmail {
+    pub code: TwoFACode,
+}
+
+pub struct VerificationEmail {
+    pub link: String,
+}
+
+pub struct PasswordResetEmail {
+    pub link: String,
+    pub expires_in_minutes: i64,
+}
+
+pub struct SecurityAlertEmail {
+    pub event: String,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl EmailTemplate for TwoFACodeEmail {
+    #[tracing::instrument(name = "Rendering 2FA code email", skip_all)]
+    fn render(&self, locale: Locale) -> Result<EmailMessage> {
+        let subject = match locale {
+            Locale::En => "Your login code",
+            Locale::Es => "Tu código de inicio de sesión",
+        };
+        let code = self.code.as_ref().expose_secret().as_str();
+
+        build_message(
+            subject,
+            TwoFACodeHtml {
+                locale,
+                subject,
+                code,
+            },
+            TwoFACodeText { locale, code },
+        )
+    }
+}
+
+impl EmailTemplate for VerificationEmail {
+    #[tracing::instrument(name = "Rendering verification email", skip_all)]
+    fn render(&self, locale: Locale) -> Result<EmailMessage> {
+        let subject = match locale {
+            Locale::En => "Verify your email address",
+            Locale::Es => "Verifica tu dirección de correo",
+        };
+        let link = self.link.as_str();
+
+        build_message(
+            subject,
+            VerificationHtml {
+                locale,
+                subject,
+                link,
+            },
+            VerificationText { locale, link },
+        )
+    }
+}
+
+impl EmailTemplate for PasswordResetEmail {
+    #[tracing::instrument(name = "Rendering password reset email", skip_all)]
+    fn render(&self, locale: Locale) -> Result<EmailMessage> {
+        let subject = match locale {
+            Locale::En => "Reset your password",
+            Locale::Es => "Restablece tu contraseña",
+        };
+        let link = self.link.as_str();
+        let expires_in_minutes = self.expires_in_minutes;
+
+        build_message(
+            subject,
+            PasswordResetHtml {
+                locale,
+                subject,
+                link,
+                expires_in_minutes,
+            },
+            PasswordResetText {
+                locale,
+                link,
+                expires_in_minutes,
+            },
+        )
+    }
+}
+
+impl EmailTemplate for SecurityAlertEmail {
+    #[tracing::instrument(name = "Rendering security alert email", skip_all)]
+    fn render(&self, locale: Locale) -> Result<EmailMessage> {
+        let subject = match locale {
+            Locale::En => "Security alert for your account",
+            Locale::Es => "Alerta de seguridad en tu cuenta",
+        };
+        let event = self.event.as_str();
+        let occurred_at = self.occurred_at.format("%Y-%m-%d %H:%M UTC").to_string();
+
+        build_message(
+            subject,
+            SecurityAlertHtml {
+                locale,
+                subject,
+                event,
+                occurred_at: &occurred_at,
+            },
+            SecurityAlertText {
+                locale,
+                event,
+                occurred_at: &occurred_at,
+            },
+        )
+    }
+}
+
+fn build_message(subject: &str, html: impl Template, text: impl Template) -> Result<EmailMessage> {
+    Ok(EmailMessage {
+        subject: subject.to_owned(),
+        html_body: html.render().wrap_err("Failed to render HTML email body")?,
+        text_body: text.render().wrap_err("Failed to render text email body")?,
+    })
+}
+
+// Askama structs backing the public templates above. Each email kind needs one
+// struct per format because a template struct is bound to a single file.
+
+#[derive(Template)]
+#[template(path = "email/two_fa_code.html")]
+struct TwoFACodeHtml<'a> {
+    locale: Locale,
+    subject: &'a str,
+    code: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "email/two_fa_code.txt")]
+struct TwoFACodeText<'a> {
+    locale: Locale,
+    code: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "email/verification.html")]
+struct VerificationHtml<'a> {
+    locale: Locale,
+    subject: &'a str,
+    link: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "email/verification.txt")]
+struct VerificationText<'a> {
+    locale: Locale,
+    link: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "email/password_reset.html")]
+struct PasswordResetHtml<'a> {
+    locale: Locale,
+    subject: &'a str,
+    link: &'a str,
+    expires_in_minutes: i64,
+}
+
+#[derive(Template)]
+#[template(path = "email/password_reset.txt")]
+struct PasswordResetText<'a> {
+    locale: Locale,
+    link: &'a str,
+    expires_in_minutes: i64,
+}
+
+#[derive(Template)]
+#[template(path = "email/security_alert.html")]
+struct SecurityAlertHtml<'a> {
+    locale: Locale,
+    subject: &'a str,
+    event: &'a str,
+    occurred_at: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "email/security_alert.txt")]
+struct SecurityAlertText<'a> {
+    locale: Locale,
+    event: &'a str,
+    occurred_at: &'a str,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn two_fa_code_email_contains_code_in_both_parts() {
+        let code = TwoFACode::default();
+        let message = TwoFACodeEmail { code: code.clone() }
+            .render(Locale::En)
+            .unwrap();
+
+        assert_eq!(message.subject, "Your login code");
+        assert!(message.html_body.contains(&code.to_string()));
+        assert!(message.text_body.contains(&code.to_string()));
+        assert!(message.html_body.contains(r#"lang="en""#));
+    }
+
+    #[test]
+    fn two_fa_code_email_is_localized() {
+        let message = TwoFACodeEmail {
+            code: TwoFACode::default(),
+        }
+        .render(Locale::Es)
+        .unwrap();
+
+        assert_eq!(message.subject, "Tu código de inicio de sesión");
+        assert!(message.text_body.starts_with("Usa el siguiente código"));
+        assert!(message.html_body.contains(r#"lang="es""#));
+    }
+
+    #[test]
+    fn html_part_escapes_values_but_text_part_does_not() {
+        let message = VerificationEmail {
+            link: "https://example.com/verify?token=a&b".to_owned(),
+        }
+        .render(Locale::En)
+        .unwrap();
+
+        assert!(message
+            .html_body
+            .contains("https://example.com/verify?token=a&amp;b"));
+        assert!(message
+            .text_body
+            .contains("https://example.com/verify?token=a&b"));
+    }
+}
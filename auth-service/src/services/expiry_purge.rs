@@ -0,0 +1,157 @@
+use crate::app_state::{BannedTokenStoreType, TwoFACodeStoreType};
+use color_eyre::eyre::{Context, Result};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+// Background task deleting expired banned tokens and 2FA codes from the stores that don't
+// expire them on their own, e.g. Postgres. Several instances may run it, deleting is idempotent.
+pub struct ExpiryPurgeWorker {
+    banned_token_store: BannedTokenStoreType,
+    two_fa_code_store: TwoFACodeStoreType,
+    interval: Duration,
+}
+
+impl ExpiryPurgeWorker {
+    pub fn new(
+        banned_token_store: BannedTokenStoreType,
+        two_fa_code_store: TwoFACodeStoreType,
+        interval: Duration,
+    ) -> Self {
+        Self {
+            banned_token_store,
+            two_fa_code_store,
+            interval,
+        }
+    }
+
+    // Purges every `interval` until `stop` is cancelled, starting right away
+    pub async fn run(self, stop: CancellationToken) {
+        let mut interval = tokio::time::interval(self.interval);
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = stop.cancelled() => break,
+            }
+
+            if let Err(e) = self.purge_expired().await {
+                tracing::error!("Failed to purge expired entries: {:?}", e);
+            }
+        }
+
+        tracing::info!("Expiry purge worker stopped");
+    }
+
+    // Deletes what has expired, returning how many entries were deleted.
+    #[tracing::instrument(name = "Purging expired entries", skip_all)]
+    pub async fn purge_expired(&self) -> Result<u64> {
+        let tokens = self
+            .banned_token_store
+            .purge_expired()
+            .await
+            .wrap_err("Failed to purge expired banned tokens")?;
+        let codes = self
+            .two_fa_code_store
+            .purge_expired()
+            .await
+            .wrap_err("Failed to purge expired 2FA codes")?;
+
+        if tokens + codes > 0 {
+            tracing::debug!(tokens, codes, "Purged expired entries");
+        }
+        Ok(tokens + codes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::data_stores::{BannedTokenStore, BannedTokenStoreError},
+        services::data_stores::{HashmapTwoFACodeStore, HashsetBannedTokenStore},
+    };
+    use secrecy::Secret;
+    use std::sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    };
+
+    // One token expires before every purge
+    #[derive(Default)]
+    struct ExpiringBannedTokenStore {
+        purges: AtomicU64,
+    }
+
+    #[async_trait::async_trait]
+    impl BannedTokenStore for ExpiringBannedTokenStore {
+        async fn add_token(
+            &self,
+            _: Secret<String>,
+            _: usize,
+        ) -> Result<(), BannedTokenStoreError> {
+            Ok(())
+        }
+
+        async fn contains_token(&self, _: Secret<String>) -> Result<bool, BannedTokenStoreError> {
+            Ok(false)
+        }
+
+        async fn empty_store(&self) -> Result<(), BannedTokenStoreError> {
+            Ok(())
+        }
+
+        async fn purge_expired(&self) -> Result<u64, BannedTokenStoreError> {
+            self.purges.fetch_add(1, Ordering::SeqCst);
+            Ok(1)
+        }
+    }
+
+    #[tokio::test]
+    async fn purges_expired_tokens() {
+        let worker = ExpiryPurgeWorker::new(
+            Arc::new(ExpiringBannedTokenStore::default()),
+            Arc::new(HashmapTwoFACodeStore::default()),
+            Duration::from_secs(60),
+        );
+
+        assert_eq!(worker.purge_expired().await.unwrap(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn purges_every_interval() {
+        let banned_token_store = Arc::new(ExpiringBannedTokenStore::default());
+        let worker = ExpiryPurgeWorker::new(
+            banned_token_store.clone(),
+            Arc::new(HashmapTwoFACodeStore::default()),
+            Duration::from_secs(60),
+        );
+        let stop = CancellationToken::new();
+
+        let handle = tokio::spawn(worker.run(stop.clone()));
+        // The clock only moves once every task is idle
+        tokio::time::sleep(Duration::from_secs(150)).await;
+        stop.cancel();
+        handle.await.unwrap();
+
+        // Right away, then after 60 and 120 seconds
+        assert_eq!(banned_token_store.purges.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn worker_stops_when_cancelled() {
+        let worker = ExpiryPurgeWorker::new(
+            Arc::new(HashsetBannedTokenStore::default()),
+            Arc::new(HashmapTwoFACodeStore::default()),
+            Duration::from_secs(60),
+        );
+        let stop = CancellationToken::new();
+
+        let handle = tokio::spawn(worker.run(stop.clone()));
+        stop.cancel();
+
+        tokio::time::timeout(Duration::from_secs(1), handle)
+            .await
+            .expect("The worker didn't stop")
+            .unwrap();
+    }
+}
@@ -0,0 +1,324 @@
+use crate::{
+    domain::{Email, EmailClient, EmailMessage},
+    services::smtp_email_client::build_mime_message,
+};
+use chrono::Utc;
+use color_eyre::eyre::{eyre, Context, Report, Result};
+use mail_parser::MessageParser;
+use regex_automata::meta::Regex;
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use uuid::Uuid;
+
+// Maildir file names end with the host that delivered the message
+const MAILDIR_HOSTNAME: &str = "auth-service";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MailboxFormat {
+    // One `.eml` file per message, directly inside the mailbox directory
+    Eml,
+    // A maildir with `tmp`, `new` and `cur` sub-directories, readable by most mail clients
+    Maildir,
+}
+
+impl FromStr for MailboxFormat {
+    type Err = Report;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "eml" => Ok(Self::Eml),
+            "maildir" => Ok(Self::Maildir),
+            other => Err(eyre!(
+                "Invalid mailbox format: {}. Expected eml or maildir",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MailboxMessage {
+    // File name of the message, unique within the mailbox
+    pub id: String,
+    pub to: String,
+    pub subject: String,
+    pub date: String,
+    pub html_body: Option<String>,
+    pub text_body: Option<String>,
+}
+
+impl MailboxMessage {
+    // Every http(s) link in the plain text part, e.g. verification links.
+    pub fn links(&self) -> Vec<String> {
+        let Some(body) = self.text_body.as_deref() else {
+            return vec![];
+        };
+        let regex = Regex::new(r#"https?://[^\s<>"]+"#).expect("Could not build regex pattern");
+
+        regex
+            .find_iter(body)
+            .map(|found| body[found.range()].to_owned())
+            .collect()
+    }
+}
+
+// A directory on disk holding one file per email.
+#[derive(Debug, Clone)]
+pub struct FileMailbox {
+    dir: PathBuf,
+    format: MailboxFormat,
+}
+
+impl FileMailbox {
+    pub fn new(dir: impl Into<PathBuf>, format: MailboxFormat) -> Self {
+        Self {
+            dir: dir.into(),
+            format,
+        }
+    }
+
+    // Writes a raw RFC 5322 message, returning its id.
+    pub async fn store(&self, contents: &[u8]) -> Result<String> {
+        let now = Utc::now();
+        let unique = Uuid::new_v4().simple();
+
+        match self.format {
+            MailboxFormat::Eml => {
+                let id = format!("{}-{}.eml", now.format("%Y%m%dT%H%M%S%.6f"), unique);
+                tokio::fs::create_dir_all(&self.dir)
+                    .await
+                    .wrap_err("Failed to create mailbox directory")?;
+                tokio::fs::write(self.dir.join(&id), contents)
+                    .await
+                    .wrap_err("Failed to write email file")?;
+
+                Ok(id)
+            }
+            MailboxFormat::Maildir => {
+                let id = format!(
+                    "{}.M{:06}R{}.{}",
+                    now.timestamp(),
+                    now.timestamp_subsec_micros(),
+                    unique,
+                    MAILDIR_HOSTNAME
+                );
+                for sub_dir in ["tmp", "new", "cur"] {
+                    tokio::fs::create_dir_all(self.dir.join(sub_dir))
+                        .await
+                        .wrap_err("Failed to create maildir")?;
+                }
+
+                // Readers only look at `new`, so the message shows up there complete
+                let tmp_path = self.dir.join("tmp").join(&id);
+                tokio::fs::write(&tmp_path, contents)
+                    .await
+                    .wrap_err("Failed to write email file")?;
+                tokio::fs::rename(&tmp_path, self.dir.join("new").join(&id))
+                    .await
+                    .wrap_err("Failed to deliver email file to maildir")?;
+
+                Ok(id)
+            }
+        }
+    }
+
+    // Every message in the mailbox, newest first.
+    pub async fn list(&self) -> Result<Vec<MailboxMessage>> {
+        let mut ids = vec![];
+        for dir in self.message_dirs() {
+            ids.extend(list_file_names(&dir).await?);
+        }
+        ids.sort_unstable_by(|a, b| b.cmp(a));
+
+        let mut messages = vec![];
+        for id in ids {
+            if let Some(message) = self.read(&id).await? {
+                messages.push(message);
+            }
+        }
+
+        Ok(messages)
+    }
+
+    pub async fn read(&self, id: &str) -> Result<Option<MailboxMessage>> {
+        if !is_valid_id(id) {
+            return Ok(None);
+        }
+
+        for dir in self.message_dirs() {
+            match tokio::fs::read(dir.join(id)).await {
+                Ok(contents) => return parse_message(id, &contents).map(Some),
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(Report::new(e).wrap_err("Failed to read email file")),
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn message_dirs(&self) -> Vec<PathBuf> {
+        match self.format {
+            MailboxFormat::Eml => vec![self.dir.clone()],
+            MailboxFormat::Maildir => vec![self.dir.join("new"), self.dir.join("cur")],
+        }
+    }
+}
+
+// Ids come from the URL, so only plain file names are accepted
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && !id.starts_with('.')
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || ".-_:,".contains(c))
+}
+
+async fn list_file_names(dir: &Path) -> Result<Vec<String>> {
+    let mut entries = match tokio::fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        // Nothing was sent yet
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(Report::new(e).wrap_err("Failed to read mailbox directory")),
+    };
+
+    let mut names = vec![];
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .wrap_err("Failed to read mailbox directory")?
+    {
+        if !entry
+            .file_type()
+            .await
+            .wrap_err("Failed to read mailbox entry")?
+            .is_file()
+        {
+            continue;
+        }
+
+        if let Some(name) = entry.file_name().to_str().filter(|name| is_valid_id(name)) {
+            names.push(name.to_owned());
+        }
+    }
+
+    Ok(names)
+}
+
+fn parse_message(id: &str, contents: &[u8]) -> Result<MailboxMessage> {
+    let message = MessageParser::default()
+        .parse(contents)
+        .ok_or_else(|| eyre!("Failed to parse email file {}", id))?;
+
+    let to = message
+        .to()
+        .and_then(|to| to.first())
+        .and_then(|address| address.address())
+        .unwrap_or_default()
+        .to_owned();
+
+    Ok(MailboxMessage {
+        id: id.to_owned(),
+        to,
+        subject: message.subject().unwrap_or_default().to_owned(),
+        date: message
+            .date()
+            .map(|date| date.to_rfc3339())
+            .unwrap_or_default(),
+        html_body: message.body_html(0).map(|body| body.into_owned()),
+        text_body: message.body_text(0).map(|body| body.into_owned()),
+    })
+}
+
+// Writes every email to a local mailbox instead of sending it. Only meant for development.
+pub struct FileEmailClient {
+    sender: Email,
+    mailbox: FileMailbox,
+}
+
+impl FileEmailClient {
+    pub fn new(sender: Email, mailbox: FileMailbox) -> Self {
+        Self { sender, mailbox }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for FileEmailClient {
+    #[tracing::instrument(name = "Writing email to local mailbox", skip_all)]
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
+        let email = build_mime_message(&self.sender, recipient, message)?;
+        let id = self.mailbox.store(&email.formatted()).await?;
+
+        tracing::info!("Email was written to the local mailbox as {}", id);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::Secret;
+
+    fn email(address: &str) -> Email {
+        Email::parse(Secret::new(address.to_owned())).unwrap()
+    }
+
+    fn message() -> EmailMessage {
+        EmailMessage {
+            subject: "Verify your email".to_owned(),
+            html_body: "<p>Open <a href=\"https://example.com/verify?t=abc\">this link</a></p>"
+                .to_owned(),
+            text_body: "Open https://example.com/verify?t=abc".to_owned(),
+        }
+    }
+
+    async fn send_and_read_back(format: MailboxFormat) {
+        let dir = std::env::temp_dir().join(format!("mailbox-{}", Uuid::new_v4()));
+        let mailbox = FileMailbox::new(&dir, format);
+        let client = FileEmailClient::new(email("sender@example.com"), mailbox.clone());
+
+        client
+            .send_email(&email("alice@example.com"), &message())
+            .await
+            .unwrap();
+        client
+            .send_email(&email("bob@example.com"), &message())
+            .await
+            .unwrap();
+
+        let messages = mailbox.list().await.unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].to, "bob@example.com");
+        assert_eq!(messages[1].subject, "Verify your email");
+        assert_eq!(
+            messages[1].links(),
+            vec!["https://example.com/verify?t=abc"]
+        );
+
+        let read = mailbox.read(&messages[1].id).await.unwrap().unwrap();
+        assert!(read.html_body.unwrap().contains("this link"));
+
+        tokio::fs::remove_dir_all(dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_writes_and_reads_eml_files() {
+        send_and_read_back(MailboxFormat::Eml).await;
+    }
+
+    #[tokio::test]
+    async fn test_writes_and_reads_maildir() {
+        send_and_read_back(MailboxFormat::Maildir).await;
+    }
+
+    #[tokio::test]
+    async fn test_read_rejects_paths_outside_mailbox() {
+        let mailbox = FileMailbox::new(std::env::temp_dir(), MailboxFormat::Eml);
+
+        assert!(mailbox.read("../etc/passwd").await.unwrap().is_none());
+        assert!(mailbox.read(".hidden").await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,184 @@
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Locale},
+    log_error_chain,
+    routes::{
+        authenticate_user, delete_user_account, register_user, revoke_token, verify_2fa_code,
+        LoginOutcome,
+    },
+    utils::auth::validate_token,
+};
+use auth_proto::{
+    auth_server::Auth, grpc_code, login_response, DeleteUserRequest, DeleteUserResponse,
+    LoginRequest, LoginResponse, LogoutRequest, LogoutResponse, SignupRequest, SignupResponse,
+    StatusCode, TwoFactorAuth, Verify2FaRequest, Verify2FaResponse, VerifyTokenRequest,
+    VerifyTokenResponse,
+};
+use secrecy::{ExposeSecret, Secret};
+use tonic::{Request, Response, Status};
+
+// Re-exporting
+pub use auth_proto::auth_server::AuthServer;
+
+// gRPC flavour of the HTTP API. Tokens are returned in the response instead of a cookie.
+pub struct GrpcAuthService {
+    state: AppState,
+}
+
+impl GrpcAuthService {
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+}
+
+impl From<AuthAPIError> for Status {
+    fn from(error: AuthAPIError) -> Self {
+        log_error_chain(&error);
+
+        let code = error.code();
+        Status::new(grpc_code(code), code.message())
+    }
+}
+
+#[tonic::async_trait]
+impl Auth for GrpcAuthService {
+    #[tracing::instrument(name = "gRPC Signup", skip_all)]
+    async fn signup(
+        &self,
+        request: Request<SignupRequest>,
+    ) -> Result<Response<SignupResponse>, Status> {
+        let request = request.into_inner();
+
+        register_user(
+            &self.state,
+            Secret::new(request.email),
+            Secret::new(request.password),
+            request.requires_2fa,
+            request.recaptcha,
+        )
+        .await?;
+
+        Ok(Response::new(SignupResponse {
+            message: "User created successfully!".to_string(),
+        }))
+    }
+
+    #[tracing::instrument(name = "gRPC Login", skip_all)]
+    async fn login(
+        &self,
+        request: Request<LoginRequest>,
+    ) -> Result<Response<LoginResponse>, Status> {
+        let accept_language = request
+            .metadata()
+            .get("accept-language")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned);
+        let request = request.into_inner();
+        let locale = Locale::negotiate(request.locale.as_deref(), accept_language.as_deref());
+
+        let result = match authenticate_user(
+            &self.state,
+            request.email,
+            Secret::new(request.password),
+            locale,
+        )
+        .await?
+        {
+            LoginOutcome::Authenticated(token) => login_response::Result::Token(token),
+            LoginOutcome::TwoFactorRequired(login_attempt_id) => {
+                login_response::Result::TwoFactorAuth(TwoFactorAuth {
+                    message: "2FA required".to_string(),
+                    login_attempt_id: login_attempt_id.as_ref().expose_secret().to_string(),
+                })
+            }
+        };
+
+        Ok(Response::new(LoginResponse {
+            result: Some(result),
+        }))
+    }
+
+    #[tracing::instrument(name = "gRPC Verify 2FA", skip_all)]
+    async fn verify2_fa(
+        &self,
+        request: Request<Verify2FaRequest>,
+    ) -> Result<Response<Verify2FaResponse>, Status> {
+        let request = request.into_inner();
+
+        let token = verify_2fa_code(
+            &self.state,
+            Secret::new(request.email),
+            request.login_attempt_id,
+            request.two_fa_code,
+        )
+        .await?;
+
+        Ok(Response::new(Verify2FaResponse { token }))
+    }
+
+    #[tracing::instrument(name = "gRPC Verify Token", skip_all)]
+    async fn verify_token(
+        &self,
+        request: Request<VerifyTokenRequest>,
+    ) -> Result<Response<VerifyTokenResponse>, Status> {
+        let token = request.into_inner().token;
+
+        match validate_token(
+            &token,
+            &self.state.settings.jwt.secret,
+            self.state.banned_token_store.clone(),
+        )
+        .await
+        {
+            Ok(_) => Ok(Response::new(VerifyTokenResponse {
+                status: StatusCode::Ok.into(),
+            })),
+            Err(_) => Err(AuthAPIError::InvalidToken.into()),
+        }
+    }
+
+    #[tracing::instrument(name = "gRPC Logout", skip_all)]
+    async fn logout(
+        &self,
+        request: Request<LogoutRequest>,
+    ) -> Result<Response<LogoutResponse>, Status> {
+        let token = request.into_inner().token;
+
+        if token.is_empty() {
+            return Err(AuthAPIError::MissingToken.into());
+        }
+
+        revoke_token(&self.state, token).await?;
+
+        Ok(Response::new(LogoutResponse {}))
+    }
+
+    #[tracing::instrument(name = "gRPC Delete User", skip_all)]
+    async fn delete_user(
+        &self,
+        request: Request<DeleteUserRequest>,
+    ) -> Result<Response<DeleteUserResponse>, Status> {
+        let request = request.into_inner();
+
+        if request.token.is_empty() {
+            return Err(AuthAPIError::MissingToken.into());
+        }
+
+        let claims = validate_token(
+            &request.token,
+            &self.state.settings.jwt.secret,
+            self.state.banned_token_store.clone(),
+        )
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+        if claims.sub != request.email {
+            return Err(AuthAPIError::InvalidToken.into());
+        }
+
+        delete_user_account(&self.state, Secret::new(request.email)).await?;
+
+        Ok(Response::new(DeleteUserResponse {
+            message: "User deleted successfully!".to_string(),
+        }))
+    }
+}
@@ -0,0 +1,81 @@
+use crate::{
+    app_state::EmailClientType, domain::HealthCheck, services::data_stores::RedisConnection,
+};
+use color_eyre::eyre::{Context, Result};
+use sqlx::PgPool;
+
+pub struct PostgresHealthCheck {
+    pool: PgPool,
+}
+
+impl PostgresHealthCheck {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl HealthCheck for PostgresHealthCheck {
+    fn name(&self) -> &'static str {
+        "postgres"
+    }
+
+    async fn check(&self) -> Result<()> {
+        sqlx::query("SELECT 1")
+            .execute(&self.pool)
+            .await
+            .wrap_err("Failed to query Postgres")?;
+
+        Ok(())
+    }
+}
+
+// Pings over the connection shared with the stores, so it checks what the requests use. A ping
+// queued behind slow store commands can hit the timeout even though Redis itself is up
+pub struct RedisHealthCheck {
+    conn: RedisConnection,
+}
+
+impl RedisHealthCheck {
+    // Pings through the stores' connection, so a Sentinel or cluster is checked the same way
+    pub fn new(conn: RedisConnection) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl HealthCheck for RedisHealthCheck {
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+
+    async fn check(&self) -> Result<()> {
+        redis::cmd("PING")
+            .query_async::<_, String>(&mut self.conn.clone())
+            .await
+            .wrap_err("Failed to ping Redis")?;
+
+        Ok(())
+    }
+}
+
+pub struct EmailHealthCheck {
+    email_client: EmailClientType,
+}
+
+impl EmailHealthCheck {
+    pub fn new(email_client: EmailClientType) -> Self {
+        Self { email_client }
+    }
+}
+
+#[async_trait::async_trait]
+impl HealthCheck for EmailHealthCheck {
+    fn name(&self) -> &'static str {
+        "email"
+    }
+
+    async fn check(&self) -> Result<()> {
+        self.email_client.check_connection().await
+    }
+}
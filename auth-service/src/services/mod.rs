@@ -1,5 +1,12 @@
-// pub mod grpc_auth;
 pub mod aws_ses_email_client;
+pub mod capturing_email_client;
 pub mod data_stores;
+pub mod email_outbox;
+pub mod email_templates;
+pub mod expiry_purge;
+pub mod file_email_client;
+pub mod grpc_auth;
+pub mod health_checks;
 pub mod mock_email_client;
 pub mod postgres_user_store;
+pub mod smtp_email_client;
@@ -30,7 +30,7 @@ struct PostgresUser {
 #[async_trait::async_trait]
 impl UserStore for PostgresUserStore {
     #[tracing::instrument(name = "Adding user to PostgreSQL", skip_all)]
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError> {
         let password_hash = compute_password_hash(user.password.as_ref().to_owned())
             .await
             .map_err(UserStoreError::UnexpectedError)?;
@@ -43,13 +43,19 @@ impl UserStore for PostgresUserStore {
         )
         .execute(&self.pool)
         .await
-        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        .map_err(|e| match e {
+            // Another signup for the same email got there first
+            sqlx::Error::Database(e) if e.is_unique_violation() => {
+                UserStoreError::UserAlreadyExists
+            }
+            e => UserStoreError::UnexpectedError(e.into()),
+        })?;
 
         Ok(())
     }
 
     #[tracing::instrument(name = "Deleting user from PostgreSQL", skip_all)]
-    async fn delete_user(&mut self, user: User) -> Result<(), UserStoreError> {
+    async fn delete_user(&self, user: User) -> Result<(), UserStoreError> {
         let _ = sqlx::query!(
             r#"DELETE FROM users WHERE email = $1"#,
             user.email.as_ref().expose_secret()
@@ -0,0 +1,172 @@
+use crate::domain::{Email, EmailClient, EmailMessage};
+use color_eyre::eyre::{eyre, Context, Report, Result};
+use lettre::{
+    message::{Mailbox, MultiPart},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use secrecy::{ExposeSecret, Secret};
+use std::{str::FromStr, time::Duration};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtpTls {
+    // Plain text connection, only meant for local sinks such as MailHog
+    None,
+    // Connects in plain text and upgrades with STARTTLS, failing if the server can't
+    StartTls,
+    // TLS from the first byte (SMTPS)
+    Implicit,
+}
+
+impl FromStr for SmtpTls {
+    type Err = Report;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "none" => Ok(Self::None),
+            "starttls" => Ok(Self::StartTls),
+            "tls" | "implicit" => Ok(Self::Implicit),
+            other => Err(eyre!(
+                "Invalid SMTP TLS mode: {}. Expected one of: none, starttls, tls",
+                other
+            )),
+        }
+    }
+}
+
+// Builds a multipart/alternative message, shared with the other lettre based clients.
+pub(crate) fn build_mime_message(
+    sender: &Email,
+    recipient: &Email,
+    message: &EmailMessage,
+) -> Result<Message> {
+    let sender: Mailbox = sender
+        .as_ref()
+        .expose_secret()
+        .parse()
+        .wrap_err("Failed to parse sender mailbox")?;
+    let recipient: Mailbox = recipient
+        .as_ref()
+        .expose_secret()
+        .parse()
+        .wrap_err("Failed to parse recipient mailbox")?;
+
+    Message::builder()
+        .from(sender)
+        .to(recipient)
+        .subject(&message.subject)
+        .multipart(MultiPart::alternative_plain_html(
+            message.text_body.clone(),
+            message.html_body.clone(),
+        ))
+        .wrap_err("Failed to build MIME message")
+}
+
+#[derive(Debug, Clone)]
+pub struct SmtpSettings {
+    pub host: String,
+    // Falls back to the default port of the TLS mode: 25, 587 or 465
+    pub port: Option<u16>,
+    pub tls: SmtpTls,
+    pub username: Option<String>,
+    pub password: Option<Secret<String>>,
+    pub timeout: Duration,
+}
+
+pub struct SmtpEmailClient {
+    sender: Email,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpEmailClient {
+    pub fn new(sender: Email, settings: SmtpSettings) -> Result<Self> {
+        let builder = match settings.tls {
+            SmtpTls::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&settings.host)
+            }
+            SmtpTls::StartTls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&settings.host)
+                    .wrap_err("Failed to configure STARTTLS SMTP transport")?
+            }
+            SmtpTls::Implicit => AsyncSmtpTransport::<Tokio1Executor>::relay(&settings.host)
+                .wrap_err("Failed to configure TLS SMTP transport")?,
+        };
+
+        let builder = match settings.port {
+            Some(port) => builder.port(port),
+            None => builder,
+        };
+
+        let builder = match (settings.username, settings.password) {
+            (Some(username), Some(password)) => builder.credentials(Credentials::new(
+                username,
+                password.expose_secret().to_owned(),
+            )),
+            (None, None) => builder,
+            _ => return Err(eyre!("SMTP username and password must be set together")),
+        };
+
+        let transport = builder.timeout(Some(settings.timeout)).build();
+
+        Ok(Self { sender, transport })
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for SmtpEmailClient {
+    #[tracing::instrument(name = "Sending email through SMTP", skip_all)]
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
+        let email = build_mime_message(&self.sender, recipient, message)?;
+
+        let response = self
+            .transport
+            .send(email)
+            .await
+            .wrap_err("Failed to send email through SMTP")?;
+
+        tracing::debug!("Email was sent successfully with code: {}", response.code());
+
+        Ok(())
+    }
+
+    async fn check_connection(&self) -> Result<()> {
+        let connected = self
+            .transport
+            .test_connection()
+            .await
+            .wrap_err("Failed to connect to the SMTP server")?;
+
+        match connected {
+            true => Ok(()),
+            false => Err(eyre!("The SMTP server didn't accept the connection")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_tls_modes() {
+        assert_eq!("none".parse::<SmtpTls>().unwrap(), SmtpTls::None);
+        assert_eq!("STARTTLS".parse::<SmtpTls>().unwrap(), SmtpTls::StartTls);
+        assert_eq!("tls".parse::<SmtpTls>().unwrap(), SmtpTls::Implicit);
+        assert!("ssl3".parse::<SmtpTls>().is_err());
+    }
+
+    #[test]
+    fn rejects_username_without_password() {
+        let sender = Email::parse(Secret::new("sender@example.com".to_owned())).unwrap();
+        let settings = SmtpSettings {
+            host: "localhost".to_owned(),
+            port: Some(1025),
+            tls: SmtpTls::None,
+            username: Some("user".to_owned()),
+            password: None,
+            timeout: Duration::from_secs(5),
+        };
+
+        assert!(SmtpEmailClient::new(sender, settings).is_err());
+    }
+}
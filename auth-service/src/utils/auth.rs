@@ -1,11 +1,10 @@
-use super::constants::{JWT_COOKIE_NAME, JWT_SECRET};
-use crate::{app_state::BannedTokenStoreType, domain::email::Email};
-use axum_extra::extract::cookie::{Cookie, SameSite};
+use super::auth_cookie::AuthCookieSettings;
+use crate::{app_state::BannedTokenStoreType, domain::Email};
+use axum_extra::extract::cookie::Cookie;
 use chrono::{Duration, Utc};
 use color_eyre::eyre::{eyre, Context, ContextCompat, Result};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Validation};
 use secrecy::{ExposeSecret, Secret};
-use serde::{Deserialize, Serialize};
 
 // This value determines how long the JWT auth token is valid for
 pub const TOKEN_TTL_SECONDS: i64 = Duration::minutes(10).num_seconds();
@@ -16,31 +15,31 @@ pub enum GenerateTokenError {
     UnexpectedError,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Claims {
-    pub sub: String,
-    pub exp: usize,
-}
+// Re-exporting
+pub use auth_domain::Claims;
 
 #[tracing::instrument(name = "Generate Auth Cookie", skip_all)]
-pub fn generate_auth_cookie(email: &Email) -> Result<Cookie<'static>> {
-    let token = generate_auth_token(email)?;
-    Ok(create_auth_cookie(token))
+pub fn generate_auth_cookie(
+    email: &Email,
+    jwt_secret: &Secret<String>,
+    cookie_settings: &AuthCookieSettings,
+) -> Result<Cookie<'static>> {
+    let token = generate_auth_token(email, jwt_secret)?;
+    Ok(create_auth_cookie(cookie_settings, token))
 }
 
 #[tracing::instrument(name = "Create Auth Cookie", skip_all)]
-fn create_auth_cookie(token: String) -> Cookie<'static> {
-    let cookie = Cookie::build((JWT_COOKIE_NAME, token))
-        .path("/") // apple cookie to all URLs on the server
-        .http_only(true) // prevent JavaScript from accessing the cookie
-        .same_site(SameSite::Lax) // send cookie with "same-site" requests, and with "cross-site" top-level navigations.
-        .build();
-
-    cookie
+pub fn create_auth_cookie(cookie_settings: &AuthCookieSettings, token: String) -> Cookie<'static> {
+    cookie_settings.build(token)
+}
+
+// Cookie that makes the browser drop the auth cookie on logout
+pub fn remove_auth_cookie(cookie_settings: &AuthCookieSettings) -> Cookie<'static> {
+    cookie_settings.removal()
 }
 
 #[tracing::instrument(name = "Generate Auth Token", skip_all)]
-pub fn generate_auth_token(email: &Email) -> Result<String> {
+pub fn generate_auth_token(email: &Email, jwt_secret: &Secret<String>) -> Result<String> {
     let delta = chrono::Duration::try_seconds(TOKEN_TTL_SECONDS)
         .wrap_err("Failed to create 10 minutes time delta")?;
 
@@ -58,17 +57,16 @@ pub fn generate_auth_token(email: &Email) -> Result<String> {
     let sub = email.as_ref().expose_secret().to_owned();
     let claims = Claims { sub, exp };
 
-    create_token(&claims)
+    create_token(&claims, jwt_secret)
 }
 
 #[tracing::instrument(name = "Validate Token", skip_all)]
 pub async fn validate_token(
     token: &str,
+    jwt_secret: &Secret<String>,
     banned_token_store: BannedTokenStoreType,
 ) -> Result<Claims> {
     match banned_token_store
-        .read()
-        .await
         .contains_token(Secret::new(token.to_string()))
         .await
     {
@@ -82,21 +80,25 @@ pub async fn validate_token(
         }
     }
 
+    // No leeway, a token the banned token store may already have dropped must be rejected
+    let mut validation = Validation::default();
+    validation.leeway = 0;
+
     decode::<Claims>(
         token,
-        &DecodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
-        &Validation::default(),
+        &DecodingKey::from_secret(jwt_secret.expose_secret().as_bytes()),
+        &validation,
     )
     .map(|data| data.claims)
     .wrap_err("Failed to decode token")
 }
 
 #[tracing::instrument(name = "Create Token", skip_all)]
-pub fn create_token(claims: &Claims) -> Result<String> {
+pub fn create_token(claims: &Claims, jwt_secret: &Secret<String>) -> Result<String> {
     encode(
         &jsonwebtoken::Header::default(),
         &claims,
-        &EncodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
+        &EncodingKey::from_secret(jwt_secret.expose_secret().as_bytes()),
     )
     .wrap_err("Failed to create token")
 }
@@ -104,19 +106,23 @@ pub fn create_token(claims: &Claims) -> Result<String> {
 #[cfg(test)]
 mod tests {
     use crate::{
-        services::data_stores::HashsetBannedTokenStore, utils::constants::env::JWT_SECRET_ENV_VAR,
+        services::data_stores::HashsetBannedTokenStore, utils::constants::JWT_COOKIE_NAME,
     };
+    use axum_extra::extract::cookie::SameSite;
     use secrecy::Secret;
-    use std::{env, sync::Arc};
-    use tokio::sync::RwLock;
+    use std::sync::Arc;
 
     use super::*;
 
+    fn jwt_secret() -> Secret<String> {
+        Secret::new("test".to_owned())
+    }
+
     #[tokio::test]
     async fn test_generate_auth_cookie() {
-        env::set_var(JWT_SECRET_ENV_VAR, "test");
         let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
-        let cookie = generate_auth_cookie(&email).unwrap();
+        let cookie =
+            generate_auth_cookie(&email, &jwt_secret(), &AuthCookieSettings::local()).unwrap();
         assert_eq!(cookie.name(), JWT_COOKIE_NAME);
         assert_eq!(cookie.value().split('.').count(), 3);
         assert_eq!(cookie.path(), Some("/"));
@@ -127,29 +133,33 @@ mod tests {
     #[tokio::test]
     async fn test_create_auth_cookie() {
         let token = "test_token".to_owned();
-        let cookie = create_auth_cookie(token.clone());
+        let cookie = create_auth_cookie(&AuthCookieSettings::local(), token.clone());
         assert_eq!(cookie.name(), JWT_COOKIE_NAME);
         assert_eq!(cookie.value(), token);
         assert_eq!(cookie.path(), Some("/"));
         assert_eq!(cookie.http_only(), Some(true));
         assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+        assert_eq!(
+            cookie.max_age(),
+            Some(time::Duration::seconds(TOKEN_TTL_SECONDS))
+        );
     }
 
     #[tokio::test]
     async fn test_generate_auth_token() {
-        env::set_var(JWT_SECRET_ENV_VAR, "test");
         let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
-        let result = generate_auth_token(&email).unwrap();
+        let result = generate_auth_token(&email, &jwt_secret()).unwrap();
         assert_eq!(result.split('.').count(), 3);
     }
 
     #[tokio::test]
     async fn test_validate_token_with_valid_token() {
-        env::set_var(JWT_SECRET_ENV_VAR, "test");
         let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
-        let token = generate_auth_token(&email).unwrap();
-        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
-        let result = validate_token(&token, banned_token_store).await.unwrap();
+        let token = generate_auth_token(&email, &jwt_secret()).unwrap();
+        let banned_token_store = Arc::new(HashsetBannedTokenStore::default());
+        let result = validate_token(&token, &jwt_secret(), banned_token_store)
+            .await
+            .unwrap();
         assert_eq!(result.sub, "test@example.com");
 
         let exp = Utc::now()
@@ -160,11 +170,23 @@ mod tests {
         assert!(result.exp > exp as usize);
     }
 
+    #[tokio::test]
+    async fn test_validate_token_with_expired_token() {
+        let claims = Claims {
+            sub: "test@example.com".to_owned(),
+            exp: (Utc::now().timestamp() - 1) as usize,
+        };
+        let token = create_token(&claims, &jwt_secret()).unwrap();
+        let banned_token_store = Arc::new(HashsetBannedTokenStore::default());
+        let result = validate_token(&token, &jwt_secret(), banned_token_store).await;
+        assert!(result.is_err());
+    }
+
     #[tokio::test]
     async fn test_validate_token_with_invalid_token() {
         let token = "invalid_token".to_owned();
-        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
-        let result = validate_token(&token, banned_token_store).await;
+        let banned_token_store = Arc::new(HashsetBannedTokenStore::default());
+        let result = validate_token(&token, &jwt_secret(), banned_token_store).await;
         assert!(result.is_err());
     }
 }
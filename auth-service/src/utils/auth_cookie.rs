@@ -0,0 +1,180 @@
+use super::{
+    auth::TOKEN_TTL_SECONDS,
+    constants::{CSRF_COOKIE_NAME, JWT_COOKIE_NAME},
+};
+use axum_extra::extract::cookie::{Cookie, SameSite};
+
+// Browsers only accept cookies with this prefix when they are Secure, have Path=/ and no
+// Domain, so they can't be set or overwritten by other subdomains
+pub const HOST_PREFIX: &str = "__Host-";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthCookieSettings {
+    // Only send the cookie over HTTPS
+    pub secure: bool,
+    // Also send the cookie to subdomains of this domain, host-only when None
+    pub domain: Option<String>,
+    // Name the cookie `__Host-jwt` instead of `jwt`
+    pub host_prefix: bool,
+    pub same_site: SameSite,
+    // Matches the JWT TTL, so the cookie doesn't outlive the token it holds
+    pub max_age_seconds: i64,
+}
+
+impl AuthCookieSettings {
+    // Plain HTTP on localhost
+    pub fn local() -> Self {
+        Self {
+            secure: false,
+            domain: None,
+            host_prefix: false,
+            same_site: SameSite::Lax,
+            max_age_seconds: TOKEN_TTL_SECONDS,
+        }
+    }
+
+    // Served over HTTPS behind the reverse proxy
+    pub fn remote() -> Self {
+        Self {
+            secure: true,
+            domain: None,
+            host_prefix: true,
+            same_site: SameSite::Lax,
+            max_age_seconds: TOKEN_TTL_SECONDS,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.host_prefix && !self.secure {
+            return Err(format!("{} cookies must be Secure", HOST_PREFIX));
+        }
+        if self.host_prefix && self.domain.is_some() {
+            return Err(format!("{} cookies can't have a Domain", HOST_PREFIX));
+        }
+        if self.same_site == SameSite::None && !self.secure {
+            return Err("SameSite=None cookies must be Secure".to_owned());
+        }
+
+        Ok(())
+    }
+
+    pub fn cookie_name(&self) -> String {
+        self.prefixed(JWT_COOKIE_NAME)
+    }
+
+    // The CSRF cookie shares the auth cookie attributes, so it's sent along with it
+    pub fn csrf_cookie_name(&self) -> String {
+        self.prefixed(CSRF_COOKIE_NAME)
+    }
+
+    pub fn build(&self, token: String) -> Cookie<'static> {
+        let mut cookie = self.base_cookie(self.cookie_name(), token);
+        cookie.set_max_age(time::Duration::seconds(self.max_age_seconds));
+        cookie
+    }
+
+    // Session cookie, it outlives the auth cookie so logging in again doesn't need a new token
+    pub fn build_csrf(&self, csrf_token: String) -> Cookie<'static> {
+        self.base_cookie(self.csrf_cookie_name(), csrf_token)
+    }
+
+    // Cookie that makes the browser drop the auth cookie, it must match its path and domain
+    pub fn removal(&self) -> Cookie<'static> {
+        let mut cookie = self.base_cookie(self.cookie_name(), String::new());
+        cookie.make_removal();
+        cookie
+    }
+
+    fn prefixed(&self, name: &str) -> String {
+        if self.host_prefix {
+            format!("{}{}", HOST_PREFIX, name)
+        } else {
+            name.to_owned()
+        }
+    }
+
+    fn base_cookie(&self, name: String, value: String) -> Cookie<'static> {
+        let mut cookie = Cookie::build((name, value))
+            .path("/") // apply cookie to all URLs on the server
+            .http_only(true) // prevent JavaScript from accessing the cookie
+            .secure(self.secure)
+            .same_site(self.same_site)
+            .build();
+
+        if let Some(domain) = &self.domain {
+            cookie.set_domain(domain.clone());
+        }
+
+        cookie
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_cookie_works_over_plain_http() {
+        let cookie = AuthCookieSettings::local().build("token".to_owned());
+
+        assert_eq!(cookie.name(), JWT_COOKIE_NAME);
+        assert_eq!(cookie.value(), "token");
+        assert_eq!(cookie.path(), Some("/"));
+        assert_eq!(cookie.http_only(), Some(true));
+        assert_eq!(cookie.secure(), Some(false));
+        assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+        assert_eq!(
+            cookie.max_age(),
+            Some(time::Duration::seconds(TOKEN_TTL_SECONDS))
+        );
+    }
+
+    #[test]
+    fn remote_cookie_is_secure_and_host_prefixed() {
+        let settings = AuthCookieSettings::remote();
+        let cookie = settings.build("token".to_owned());
+
+        assert!(settings.validate().is_ok());
+        assert_eq!(cookie.name(), "__Host-jwt");
+        assert_eq!(cookie.secure(), Some(true));
+        assert_eq!(cookie.domain(), None);
+        assert_eq!(cookie.path(), Some("/"));
+        assert_eq!(settings.csrf_cookie_name(), "__Host-csrf");
+    }
+
+    #[test]
+    fn removal_cookie_matches_auth_cookie() {
+        let settings = AuthCookieSettings {
+            domain: Some("example.com".to_owned()),
+            host_prefix: false,
+            ..AuthCookieSettings::remote()
+        };
+        let cookie = settings.removal();
+
+        assert_eq!(cookie.name(), JWT_COOKIE_NAME);
+        assert_eq!(cookie.value(), "");
+        assert_eq!(cookie.domain(), Some("example.com"));
+        assert_eq!(cookie.path(), Some("/"));
+        assert_eq!(cookie.max_age(), Some(time::Duration::ZERO));
+    }
+
+    #[test]
+    fn rejects_inconsistent_settings() {
+        let insecure_host_prefix = AuthCookieSettings {
+            secure: false,
+            ..AuthCookieSettings::remote()
+        };
+        let host_prefix_with_domain = AuthCookieSettings {
+            domain: Some("example.com".to_owned()),
+            ..AuthCookieSettings::remote()
+        };
+        let insecure_same_site_none = AuthCookieSettings {
+            same_site: SameSite::None,
+            ..AuthCookieSettings::local()
+        };
+
+        assert!(insecure_host_prefix.validate().is_err());
+        assert!(host_prefix_with_domain.validate().is_err());
+        assert!(insecure_same_site_none.validate().is_err());
+    }
+}
@@ -1,119 +1,65 @@
-use dotenvy::dotenv;
-use lazy_static::lazy_static;
-use secrecy::Secret;
-use std::env as std_env;
-
-lazy_static! {
-    pub static ref JWT_SECRET: Secret<String> = set_token();
-    pub static ref DATABASE_URL: Secret<String> = set_database_url();
-    pub static ref REDIS_HOST_NAME: String = set_redis_hostname();
-    // AWS
-    pub static ref AWS_ACCESS_KEY_ID: Secret<String> = set_access_key_id();
-    pub static ref AWS_SECRET_ACCESS_KEY: Secret<String> = set_aws_secret_access_key();
-    pub static ref AWS_DEFAULT_REGION: Secret<String> = set_aws_region();
-    // EmailClient
-    pub static ref EMAIL_SENDER: String = set_email_client_sender();
-}
-
-fn set_token() -> Secret<String> {
-    dotenv().ok();
-    let secret = std_env::var(env::JWT_SECRET_ENV_VAR).expect("JWT_SECRET must be set.");
-
-    if secret.is_empty() {
-        panic!("JWT_SECRET must not be empty.");
-    }
-
-    Secret::new(secret)
-}
-
-fn set_email_client_sender() -> String {
-    dotenv().ok();
-    let secret = std_env::var(env::EMAIL_SENDER_NAME_ENV_VAR).expect("EMAIL_SENDER must be set.");
-
-    if secret.is_empty() {
-        panic!("EMAIL_SENDER must not be empty.");
-    }
-
-    secret
-}
-
-fn set_database_url() -> Secret<String> {
-    dotenv().ok();
-    let secret = std_env::var(env::DATABASE_URL_ENV_VAR).expect("DATABASE_URL must be set.");
-
-    if secret.is_empty() {
-        panic!("DATABASE_URL must not be empty.");
-    }
-    Secret::new(secret)
-}
-
-fn set_redis_hostname() -> String {
-    dotenv().ok();
-    let secret =
-        std_env::var(env::REDIS_HOST_NAME_ENV_VAR).unwrap_or(DEFAULT_REDIS_HOSTNAME.to_owned());
-
-    if secret.is_empty() {
-        panic!("REDIS_HOST_NAME must not be empty.");
-    }
-    secret
-}
-
-fn set_access_key_id() -> Secret<String> {
-    dotenv().ok();
-    let secret =
-        std_env::var(env::AWS_ACCESS_KEY_ID_NAME_ENV_VAR).expect("AWS_ACCESS_KEY_ID must be set.");
-
-    if secret.is_empty() {
-        panic!("DATABASE_URL must not be empty.");
-    }
-
-    Secret::new(secret)
-}
-
-fn set_aws_secret_access_key() -> Secret<String> {
-    dotenv().ok();
-    let secret = std_env::var(env::AWS_SECRET_ACCESS_KEY_NAME_ENV_VAR)
-        .expect("AWS_SECRET_ACCESS_KEY must be set.");
-
-    if secret.is_empty() {
-        panic!("DATABASE_URL must not be empty.");
-    }
-
-    Secret::new(secret)
-}
-
-fn set_aws_region() -> Secret<String> {
-    dotenv().ok();
-    let secret = std_env::var(env::AWS_DEFAULT_REGION_NAME_ENV_VAR)
-        .expect("AWS_DEFAULT_REGION must be set.");
-
-    if secret.is_empty() {
-        panic!("DATABASE_URL must not be empty.");
-    }
-
-    Secret::new(secret)
-}
-
 pub const JWT_COOKIE_NAME: &str = "jwt";
-pub const DEFAULT_REDIS_HOSTNAME: &str = "127.0.0.1";
+pub const CSRF_COOKIE_NAME: &str = "csrf";
+pub const CSRF_HEADER_NAME: &str = "x-csrf-token";
+pub const SMTP_TIMEOUT_SECONDS: u64 = 10;
+pub const DEFAULT_CONFIG_DIR: &str = "config";
 
 pub mod env {
+    pub const CONFIG_DIR_ENV_VAR: &str = "CONFIG_DIR";
+    pub const ENVIRONMENT_ENV_VAR: &str = "ENVIRONMENT";
+    pub const APP_ADDRESS_ENV_VAR: &str = "APP_ADDRESS";
+    pub const GRPC_APP_ADDRESS_ENV_VAR: &str = "GRPC_APP_ADDRESS";
     pub const JWT_SECRET_ENV_VAR: &str = "JWT_SECRET";
-    pub const BASE_PATH_ENV_VAR: &str = "BASE_PATH";
-    pub const DROPLET_IP_ENV_VAR: &str = "DROPLET_IP";
     pub const RECAPTCHA_SECRET_ENV_VAR: &str = "RECAPTCHA_SECRET";
     pub const DATABASE_URL_ENV_VAR: &str = "DATABASE_URL";
     pub const REDIS_HOST_NAME_ENV_VAR: &str = "REDIS_HOST_NAME";
-    pub const AWS_ACCESS_KEY_ID_NAME_ENV_VAR: &str = "AWS_ACCESS_KEY_ID";
-    pub const AWS_SECRET_ACCESS_KEY_NAME_ENV_VAR: &str = "AWS_SECRET_ACCESS_KEY";
-    pub const AWS_DEFAULT_REGION_NAME_ENV_VAR: &str = "AWS_DEFAULT_REGION";
-    pub const EMAIL_SENDER_NAME_ENV_VAR: &str = "EMAIL_SENDER";
+    pub const REDIS_MODE_ENV_VAR: &str = "REDIS_MODE";
+    pub const REDIS_URL_ENV_VAR: &str = "REDIS_URL";
+    pub const REDIS_NODES_ENV_VAR: &str = "REDIS_NODES";
+    pub const REDIS_SENTINEL_MASTER_NAME_ENV_VAR: &str = "REDIS_SENTINEL_MASTER_NAME";
+    pub const REDIS_KEY_PREFIX_ENV_VAR: &str = "REDIS_KEY_PREFIX";
+    pub const REDIS_TIMEOUT_MILLISECONDS_ENV_VAR: &str = "REDIS_TIMEOUT_MILLISECONDS";
+    pub const STORES_BACKEND_ENV_VAR: &str = "STORES_BACKEND";
+    pub const STORES_PURGE_INTERVAL_SECONDS_ENV_VAR: &str = "STORES_PURGE_INTERVAL_SECONDS";
+    pub const EMAIL_SENDER_ENV_VAR: &str = "EMAIL_SENDER";
+    pub const EMAIL_CLIENT_ENV_VAR: &str = "EMAIL_CLIENT";
+    pub const TWO_FA_EMAIL_DELIVERY_ENV_VAR: &str = "TWO_FA_EMAIL_DELIVERY";
+    pub const SMTP_HOST_ENV_VAR: &str = "SMTP_HOST";
+    pub const SMTP_PORT_ENV_VAR: &str = "SMTP_PORT";
+    pub const SMTP_TLS_ENV_VAR: &str = "SMTP_TLS";
+    pub const SMTP_USERNAME_ENV_VAR: &str = "SMTP_USERNAME";
+    pub const SMTP_PASSWORD_ENV_VAR: &str = "SMTP_PASSWORD";
+    pub const MAILBOX_DIR_ENV_VAR: &str = "MAILBOX_DIR";
+    pub const MAILBOX_FORMAT_ENV_VAR: &str = "MAILBOX_FORMAT";
+    pub const AUTH_COOKIE_SECURE_ENV_VAR: &str = "AUTH_COOKIE_SECURE";
+    pub const AUTH_COOKIE_DOMAIN_ENV_VAR: &str = "AUTH_COOKIE_DOMAIN";
+    pub const AUTH_COOKIE_HOST_PREFIX_ENV_VAR: &str = "AUTH_COOKIE_HOST_PREFIX";
+    pub const AUTH_COOKIE_SAME_SITE_ENV_VAR: &str = "AUTH_COOKIE_SAME_SITE";
+    pub const CORS_ALLOWED_ORIGINS_ENV_VAR: &str = "CORS_ALLOWED_ORIGINS";
+    pub const CORS_ALLOWED_METHODS_ENV_VAR: &str = "CORS_ALLOWED_METHODS";
+    pub const CORS_ALLOWED_HEADERS_ENV_VAR: &str = "CORS_ALLOWED_HEADERS";
+    pub const CORS_MAX_AGE_SECONDS_ENV_VAR: &str = "CORS_MAX_AGE_SECONDS";
+    pub const HEALTH_TIMEOUT_MILLISECONDS_ENV_VAR: &str = "HEALTH_TIMEOUT_MILLISECONDS";
+    pub const HEALTH_CHECK_EMAIL_PROVIDER_ENV_VAR: &str = "HEALTH_CHECK_EMAIL_PROVIDER";
+    pub const LOG_FORMAT_ENV_VAR: &str = "LOG_FORMAT";
+    pub const SHUTDOWN_READINESS_DELAY_SECONDS_ENV_VAR: &str = "SHUTDOWN_READINESS_DELAY_SECONDS";
+    pub const SHUTDOWN_DRAIN_TIMEOUT_SECONDS_ENV_VAR: &str = "SHUTDOWN_DRAIN_TIMEOUT_SECONDS";
 }
 
-pub mod prod {
-    pub const APP_ADDRESS: &str = "0.0.0.0:3000";
+pub mod email_outbox {
+    pub const POLL_INTERVAL_MILLISECONDS: u64 = 1000;
+    pub const BATCH_SIZE: i64 = 10;
+    pub const MAX_ATTEMPTS: i32 = 8;
+    pub const BASE_BACKOFF_SECONDS: i64 = 5;
+    pub const MAX_BACKOFF_SECONDS: i64 = 60 * 60;
+    // How long a claimed email stays hidden from other workers while it's being sent
+    pub const LEASE_SECONDS: i64 = 60;
+    pub const SYNC_SEND_TIMEOUT_SECONDS: u64 = 5;
 }
 
-pub mod test {
-    pub const APP_ADDRESS: &str = "127.0.0.1:0";
+pub mod redis {
+    // Reconnection attempts wait up to 2^n * 10ms, 10ms, 20ms, 40ms... then commands fail
+    pub const REDIS_RECONNECT_BACKOFF_BASE: u64 = 2;
+    pub const REDIS_RECONNECT_BACKOFF_FACTOR_MILLISECONDS: u64 = 10;
+    pub const REDIS_RECONNECT_RETRIES: usize = 5;
 }
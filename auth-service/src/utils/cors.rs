@@ -0,0 +1,193 @@
+use axum::http::{request::Parts, HeaderName, HeaderValue, Method};
+use std::{fmt, str::FromStr, time::Duration};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+const WILDCARD_SUBDOMAIN: &str = "*.";
+
+// Browsers sending requests from another origin, with the auth cookie
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorsSettings {
+    pub allowed_origins: Vec<AllowedOrigin>,
+    pub allowed_methods: Vec<Method>,
+    pub allowed_headers: Vec<HeaderName>,
+    // How long browsers can cache a preflight response
+    pub max_age_seconds: u64,
+}
+
+impl CorsSettings {
+    // Credentials are allowed, so browsers ignore `*` in any of the lists
+    pub fn validate(&self) -> Result<(), String> {
+        if self.allowed_methods.iter().any(|method| method == "*") {
+            return Err("allowed_methods can't contain *, list the methods".to_owned());
+        }
+        if self.allowed_headers.iter().any(|header| header == "*") {
+            return Err("allowed_headers can't contain *, list the headers".to_owned());
+        }
+
+        Ok(())
+    }
+
+    pub fn layer(&self) -> CorsLayer {
+        let allowed_origins = self.allowed_origins.clone();
+
+        CorsLayer::new()
+            .allow_origin(AllowOrigin::predicate(
+                move |origin: &HeaderValue, _: &Parts| {
+                    origin.to_str().is_ok_and(|origin| {
+                        allowed_origins
+                            .iter()
+                            .any(|allowed| allowed.matches(origin))
+                    })
+                },
+            ))
+            .allow_methods(self.allowed_methods.clone())
+            .allow_headers(self.allowed_headers.clone())
+            .allow_credentials(true)
+            .max_age(Duration::from_secs(self.max_age_seconds))
+    }
+}
+
+// Either an exact origin, `https://app.example.com`, or every subdomain of a domain,
+// `https://*.example.com`, which doesn't match `https://example.com` itself
+#[derive(Debug, Clone, PartialEq)]
+pub enum AllowedOrigin {
+    Exact(String),
+    Subdomains {
+        // Scheme with `://`, e.g. `https://`
+        scheme: String,
+        // Domain and port after the wildcard, with the leading dot, e.g. `.example.com:8443`
+        suffix: String,
+    },
+}
+
+impl AllowedOrigin {
+    pub fn matches(&self, origin: &str) -> bool {
+        match self {
+            Self::Exact(allowed) => allowed.eq_ignore_ascii_case(origin),
+            Self::Subdomains { scheme, suffix } => {
+                let origin = origin.to_ascii_lowercase();
+                let Some(host) = origin
+                    .strip_prefix(scheme.as_str())
+                    .and_then(|rest| rest.strip_suffix(suffix.as_str()))
+                else {
+                    return false;
+                };
+
+                // Only DNS labels can stand in for the wildcard, so `https://evil.com/.example.com`
+                // or `https://evil.com?.example.com` don't match
+                !host.is_empty()
+                    && host
+                        .split('.')
+                        .all(|label| !label.is_empty() && label.chars().all(is_label_char))
+            }
+        }
+    }
+}
+
+impl FromStr for AllowedOrigin {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim().trim_end_matches('/').to_ascii_lowercase();
+        let invalid = |reason: &str| format!("Invalid origin {}: {}", value, reason);
+
+        let Some((scheme, authority)) = value.split_once("://") else {
+            return Err(invalid("expected scheme://host[:port]"));
+        };
+        if scheme != "http" && scheme != "https" {
+            return Err(invalid("the scheme must be http or https"));
+        }
+        if authority.contains(['/', '?', '#']) {
+            return Err(invalid("origins have no path"));
+        }
+
+        let (host, domain) = match authority.strip_prefix(WILDCARD_SUBDOMAIN) {
+            Some(domain) => (domain, Some(domain)),
+            None => (authority, None),
+        };
+        let hostname = host.split(':').next().unwrap_or_default();
+        if hostname.is_empty()
+            || !hostname
+                .chars()
+                .all(|c| is_label_char(c) || c == '.' || c == '[' || c == ']')
+        {
+            return Err(invalid(
+                "expected scheme://host[:port], or scheme://*.domain",
+            ));
+        }
+
+        match domain {
+            Some(domain) => Ok(Self::Subdomains {
+                scheme: format!("{}://", scheme),
+                suffix: format!(".{}", domain),
+            }),
+            None => Ok(Self::Exact(value)),
+        }
+    }
+}
+
+impl fmt::Display for AllowedOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Exact(origin) => write!(f, "{}", origin),
+            Self::Subdomains { scheme, suffix } => write!(f, "{}*{}", scheme, suffix),
+        }
+    }
+}
+
+fn is_label_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn origin(value: &str) -> AllowedOrigin {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn exact_origin_matches_only_itself() {
+        let allowed = origin("http://localhost:8000/");
+
+        assert_eq!(allowed.to_string(), "http://localhost:8000");
+        assert!(allowed.matches("http://localhost:8000"));
+        assert!(allowed.matches("http://LOCALHOST:8000"));
+        assert!(!allowed.matches("http://localhost:3000"));
+        assert!(!allowed.matches("https://localhost:8000"));
+    }
+
+    #[test]
+    fn wildcard_matches_subdomains_only() {
+        let allowed = origin("https://*.example.com");
+
+        assert!(allowed.matches("https://app.example.com"));
+        assert!(allowed.matches("https://a.b.example.com"));
+        assert!(!allowed.matches("https://example.com"));
+        assert!(!allowed.matches("http://app.example.com"));
+        assert!(!allowed.matches("https://app.example.com:8443"));
+        assert!(!allowed.matches("https://evilexample.com"));
+        assert!(!allowed.matches("https://evil.com/.example.com"));
+        assert!(!allowed.matches("https://evil.com?.example.com"));
+        assert!(!allowed.matches("https://.example.com"));
+
+        let with_port = origin("https://*.example.com:8443");
+        assert!(with_port.matches("https://app.example.com:8443"));
+        assert!(!with_port.matches("https://app.example.com"));
+    }
+
+    #[test]
+    fn rejects_invalid_origins() {
+        for value in [
+            "*",
+            "localhost:8000",
+            "ftp://example.com",
+            "https://example.com/app",
+            "https://*",
+            "https://app.*.example.com",
+        ] {
+            assert!(value.parse::<AllowedOrigin>().is_err(), "{}", value);
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use super::{
+    auth_cookie::AuthCookieSettings,
+    constants::CSRF_HEADER_NAME,
+    extractors::{bearer_token, cookie_token},
+};
+use crate::domain::AuthAPIError;
+use axum::{
+    body::Body,
+    http::{HeaderMap, Method, Request},
+    response::{IntoResponse, Response},
+};
+use axum_extra::extract::CookieJar;
+use rand::RngCore;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use subtle::ConstantTimeEq;
+use tower::{Layer, Service};
+
+const CSRF_TOKEN_BYTES: usize = 32;
+
+// Random token for the double-submit check, hex encoded so it's safe in cookies and headers
+pub fn generate_csrf_token() -> String {
+    let mut bytes = [0u8; CSRF_TOKEN_BYTES];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// Token in the CSRF cookie, if the client already got one
+pub fn csrf_cookie_token(
+    headers: &HeaderMap,
+    cookie_settings: &AuthCookieSettings,
+) -> Option<String> {
+    CookieJar::from_headers(headers)
+        .get(&cookie_settings.csrf_cookie_name())
+        .map(|cookie| cookie.value().to_owned())
+        .filter(|token| !token.is_empty())
+}
+
+// Double-submit cookie protection: state-changing requests that rely on the auth cookie must
+// repeat the CSRF cookie in the `X-CSRF-Token` header. Other sites can make the browser send
+// our cookies but can't read them or set custom headers, so they can't pass the check.
+//
+// Requests authenticated with `Authorization: Bearer` are exempt, browsers never attach that
+// header on their own. So are requests without the auth cookie, there's no ambient session
+// to abuse.
+#[derive(Debug, Clone)]
+pub struct CsrfLayer {
+    cookie_settings: Arc<AuthCookieSettings>,
+}
+
+impl CsrfLayer {
+    pub fn new(cookie_settings: AuthCookieSettings) -> Self {
+        Self {
+            cookie_settings: Arc::new(cookie_settings),
+        }
+    }
+}
+
+impl<S> Layer<S> for CsrfLayer {
+    type Service = CsrfService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CsrfService {
+            inner,
+            cookie_settings: self.cookie_settings.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CsrfService<S> {
+    inner: S,
+    cookie_settings: Arc<AuthCookieSettings>,
+}
+
+impl<S> Service<Request<Body>> for CsrfService<S>
+where
+    S: Service<Request<Body>, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let headers = request.headers();
+        if requires_csrf_token(request.method(), headers, &self.cookie_settings)
+            && !has_valid_csrf_token(headers, &self.cookie_settings)
+        {
+            tracing::warn!("Rejected request without a valid CSRF token");
+            return Box::pin(async { Ok(AuthAPIError::InvalidCsrfToken.into_response()) });
+        }
+
+        // The clone may not be ready, keep the instance poll_ready was called on
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(inner.call(request))
+    }
+}
+
+fn requires_csrf_token(
+    method: &Method,
+    headers: &HeaderMap,
+    cookie_settings: &AuthCookieSettings,
+) -> bool {
+    let is_safe = matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+    );
+
+    !is_safe && bearer_token(headers).is_none() && cookie_token(headers, cookie_settings).is_some()
+}
+
+fn has_valid_csrf_token(headers: &HeaderMap, cookie_settings: &AuthCookieSettings) -> bool {
+    let header_token = headers
+        .get(CSRF_HEADER_NAME)
+        .and_then(|value| value.to_str().ok());
+
+    match (header_token, csrf_cookie_token(headers, cookie_settings)) {
+        // The time taken doesn't tell how much of the token was right
+        (Some(header_token), Some(cookie_token)) => header_token
+            .as_bytes()
+            .ct_eq(cookie_token.as_bytes())
+            .into(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::header::{AUTHORIZATION, COOKIE};
+
+    fn requires_token(method: Method, headers: &HeaderMap) -> bool {
+        requires_csrf_token(&method, headers, &AuthCookieSettings::local())
+    }
+
+    fn is_valid(headers: &HeaderMap) -> bool {
+        has_valid_csrf_token(headers, &AuthCookieSettings::local())
+    }
+
+    fn headers(pairs: &[(&str, &str)]) -> HeaderMap {
+        let mut request = Request::builder();
+        for (name, value) in pairs {
+            request = request.header(*name, *value);
+        }
+        request.body(()).unwrap().into_parts().0.headers
+    }
+
+    #[test]
+    fn only_cookie_authenticated_unsafe_requests_need_a_token() {
+        let with_cookie = headers(&[(COOKIE.as_str(), "jwt=token")]);
+        let with_bearer = headers(&[
+            (COOKIE.as_str(), "jwt=token"),
+            (AUTHORIZATION.as_str(), "Bearer token"),
+        ]);
+
+        assert!(requires_token(Method::POST, &with_cookie));
+        assert!(requires_token(Method::DELETE, &with_cookie));
+        assert!(!requires_token(Method::GET, &with_cookie));
+        assert!(!requires_token(Method::POST, &with_bearer));
+        assert!(!requires_token(Method::POST, &headers(&[])));
+    }
+
+    #[test]
+    fn header_must_match_cookie() {
+        let token = generate_csrf_token();
+        let cookie = format!("jwt=token; csrf={}", token);
+
+        assert_eq!(token.len(), CSRF_TOKEN_BYTES * 2);
+        assert!(is_valid(&headers(&[
+            (COOKIE.as_str(), &cookie),
+            (CSRF_HEADER_NAME, &token),
+        ])));
+        assert!(!is_valid(&headers(&[
+            (COOKIE.as_str(), &cookie),
+            (CSRF_HEADER_NAME, &generate_csrf_token()),
+        ])));
+        assert!(!is_valid(&headers(&[(COOKIE.as_str(), &cookie)])));
+        assert!(!is_valid(&headers(&[
+            (COOKIE.as_str(), "jwt=token"),
+            (CSRF_HEADER_NAME, &token),
+        ])));
+    }
+}
@@ -0,0 +1,126 @@
+use super::{
+    auth::{validate_token, Claims},
+    auth_cookie::AuthCookieSettings,
+};
+use crate::{app_state::AppState, domain::AuthAPIError};
+use axum::{
+    extract::FromRequestParts,
+    http::{header::AUTHORIZATION, request::Parts, HeaderMap},
+};
+use axum_extra::extract::CookieJar;
+
+// Token sent with the request, either as `Authorization: Bearer <token>` (mobile and CLI
+// clients) or as the JWT cookie (browsers). The header wins when both are present.
+#[derive(Debug)]
+pub struct AuthToken(pub String);
+
+#[async_trait::async_trait]
+impl FromRequestParts<AppState> for AuthToken {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        request_token(&parts.headers, &state.settings.auth_cookie)
+            .map(AuthToken)
+            .ok_or(AuthAPIError::MissingToken)
+    }
+}
+
+fn request_token(headers: &HeaderMap, cookie_settings: &AuthCookieSettings) -> Option<String> {
+    bearer_token(headers).or_else(|| cookie_token(headers, cookie_settings))
+}
+
+pub(crate) fn bearer_token(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get(AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+
+    if !scheme.eq_ignore_ascii_case("bearer") || token.trim().is_empty() {
+        return None;
+    }
+
+    Some(token.trim().to_owned())
+}
+
+pub(crate) fn cookie_token(
+    headers: &HeaderMap,
+    cookie_settings: &AuthCookieSettings,
+) -> Option<String> {
+    CookieJar::from_headers(headers)
+        .get(&cookie_settings.cookie_name())
+        .map(|cookie| cookie.value().to_owned())
+        .filter(|token| !token.is_empty())
+}
+
+// Requires a valid and not banned token, for routes that only make sense for logged in users.
+#[derive(Debug)]
+pub struct AuthenticatedUser {
+    pub claims: Claims,
+    pub token: String,
+}
+
+#[async_trait::async_trait]
+impl FromRequestParts<AppState> for AuthenticatedUser {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let AuthToken(token) = AuthToken::from_request_parts(parts, state).await?;
+
+        let claims = validate_token(
+            &token,
+            &state.settings.jwt.secret,
+            state.banned_token_store.clone(),
+        )
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+        Ok(Self { claims, token })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::{header::COOKIE, Request};
+
+    fn extract(headers: &[(&str, &str)]) -> Option<String> {
+        let mut request = Request::builder();
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let (parts, _) = request.body(()).unwrap().into_parts();
+
+        request_token(&parts.headers, &AuthCookieSettings::local())
+    }
+
+    #[test]
+    fn reads_token_from_bearer_header_or_cookie() {
+        assert_eq!(
+            extract(&[(AUTHORIZATION.as_str(), "Bearer header-token")]),
+            Some("header-token".to_owned())
+        );
+        assert_eq!(
+            extract(&[(COOKIE.as_str(), "jwt=cookie-token")]),
+            Some("cookie-token".to_owned())
+        );
+        assert_eq!(
+            extract(&[
+                (AUTHORIZATION.as_str(), "bearer header-token"),
+                (COOKIE.as_str(), "jwt=cookie-token"),
+            ]),
+            Some("header-token".to_owned())
+        );
+    }
+
+    #[test]
+    fn rejects_requests_without_token() {
+        assert_eq!(extract(&[]), None);
+        assert_eq!(extract(&[(AUTHORIZATION.as_str(), "Basic abc")]), None);
+        assert_eq!(extract(&[(AUTHORIZATION.as_str(), "Bearer ")]), None);
+        assert_eq!(extract(&[(COOKIE.as_str(), "jwt=")]), None);
+    }
+}
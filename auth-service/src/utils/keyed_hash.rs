@@ -0,0 +1,73 @@
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, Secret};
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// Separates the store key from the JWT signing key it's derived from
+const STORE_KEY_CONTEXT: &[u8] = b"auth-service data store keys";
+
+// HMAC-SHA256 of the tokens and 2FA codes kept in the data stores, so reading the store isn't
+// enough to replay a token or complete a 2FA login, nor to brute force a 6 digit code offline.
+#[derive(Clone)]
+pub struct KeyedHasher {
+    mac: HmacSha256,
+}
+
+impl KeyedHasher {
+    // Rotating the JWT secret already invalidates every token, so the stored hashes going
+    // stale with it loses nothing
+    pub fn from_jwt_secret(jwt_secret: &Secret<String>) -> Self {
+        let mut derivation = HmacSha256::new_from_slice(jwt_secret.expose_secret().as_bytes())
+            .expect("HMAC accepts keys of any length");
+        derivation.update(STORE_KEY_CONTEXT);
+        let key = derivation.finalize().into_bytes();
+
+        Self {
+            mac: HmacSha256::new_from_slice(&key).expect("HMAC accepts keys of any length"),
+        }
+    }
+
+    // Hex encoded, to be used in keys and values
+    pub fn hash(&self, value: &str) -> String {
+        let mut mac = self.mac.clone();
+        mac.update(value.as_bytes());
+
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    // Compares in constant time, so response times don't reveal how much of a guess matched
+    pub fn verify(&self, value: &str, expected_hash: &str) -> bool {
+        self.hash(value)
+            .as_bytes()
+            .ct_eq(expected_hash.as_bytes())
+            .into()
+    }
+}
+
+impl std::fmt::Debug for KeyedHasher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("KeyedHasher")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_depend_on_the_secret() {
+        let hasher = KeyedHasher::from_jwt_secret(&Secret::new("secret".to_owned()));
+        let other = KeyedHasher::from_jwt_secret(&Secret::new("other".to_owned()));
+
+        let hash = hasher.hash("123456");
+
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, hasher.hash("123456"));
+        assert_ne!(hash, other.hash("123456"));
+        assert!(hasher.verify("123456", &hash));
+        assert!(!hasher.verify("123457", &hash));
+        assert!(!other.verify("123456", &hash));
+    }
+}
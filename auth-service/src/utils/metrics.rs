@@ -0,0 +1,274 @@
+use crate::{
+    domain::{AuthAPIError, DependencyHealth},
+    routes::LoginOutcome,
+};
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use prometheus::{
+    core::{Collector, Desc},
+    proto::MetricFamily,
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use sqlx::PgPool;
+use std::{sync::Arc, time::Instant};
+
+// Label of requests that didn't match any route, so scanners can't blow up the cardinality
+pub(crate) const UNMATCHED_ROUTE: &str = "unmatched";
+const SUCCESS: &str = "success";
+
+// Everything exported on /metrics. The registry is passed in, so tests get their own instead
+// of sharing a global one.
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    http_request_duration_seconds: HistogramVec,
+    signups_total: IntCounter,
+    logins_total: IntCounterVec,
+    two_fa_codes_issued_total: IntCounter,
+    two_fa_codes_verified_total: IntCounterVec,
+    tokens_banned_total: IntCounter,
+    email_send_failures_total: IntCounter,
+    dependency_up: IntGaugeVec,
+}
+
+impl Metrics {
+    pub fn new(registry: Registry) -> prometheus::Result<Self> {
+        let metrics = Self {
+            http_request_duration_seconds: HistogramVec::new(
+                HistogramOpts::new(
+                    "http_request_duration_seconds",
+                    "Latency of HTTP requests by route and status",
+                ),
+                &["method", "route", "status"],
+            )?,
+            signups_total: IntCounter::new("auth_signups_total", "Users who signed up")?,
+            logins_total: IntCounterVec::new(
+                Opts::new(
+                    "auth_logins_total",
+                    "Login attempts by result: success, two_fa_required or the error code",
+                ),
+                &["result"],
+            )?,
+            two_fa_codes_issued_total: IntCounter::new(
+                "auth_two_fa_codes_issued_total",
+                "2FA codes sent to users",
+            )?,
+            two_fa_codes_verified_total: IntCounterVec::new(
+                Opts::new(
+                    "auth_two_fa_codes_verified_total",
+                    "2FA code checks by result: success or the error code",
+                ),
+                &["result"],
+            )?,
+            tokens_banned_total: IntCounter::new(
+                "auth_tokens_banned_total",
+                "Tokens revoked by a logout",
+            )?,
+            email_send_failures_total: IntCounter::new(
+                "auth_email_send_failures_total",
+                "Failed attempts to hand an email to the provider",
+            )?,
+            dependency_up: IntGaugeVec::new(
+                Opts::new(
+                    "auth_dependency_up",
+                    "Whether a dependency answered its last check, 1 or 0",
+                ),
+                &["dependency"],
+            )?,
+            registry,
+        };
+
+        metrics.register(Box::new(metrics.http_request_duration_seconds.clone()))?;
+        metrics.register(Box::new(metrics.signups_total.clone()))?;
+        metrics.register(Box::new(metrics.logins_total.clone()))?;
+        metrics.register(Box::new(metrics.two_fa_codes_issued_total.clone()))?;
+        metrics.register(Box::new(metrics.two_fa_codes_verified_total.clone()))?;
+        metrics.register(Box::new(metrics.tokens_banned_total.clone()))?;
+        metrics.register(Box::new(metrics.email_send_failures_total.clone()))?;
+        metrics.register(Box::new(metrics.dependency_up.clone()))?;
+
+        Ok(metrics)
+    }
+
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    fn register(&self, collector: Box<dyn Collector>) -> prometheus::Result<()> {
+        self.registry.register(collector)
+    }
+
+    // Exports the connections of the pool, read on every scrape
+    pub fn observe_postgres_pool(&self, pool: PgPool) -> prometheus::Result<()> {
+        self.register(Box::new(PgPoolCollector::new(pool)?))
+    }
+
+    pub fn record_signup(&self) {
+        self.signups_total.inc();
+    }
+
+    pub fn record_login(&self, result: &Result<LoginOutcome, AuthAPIError>) {
+        let label = match result {
+            Ok(LoginOutcome::Authenticated(_)) => SUCCESS,
+            Ok(LoginOutcome::TwoFactorRequired(_)) => "two_fa_required",
+            Err(e) => e.code().as_str(),
+        };
+        self.logins_total.with_label_values(&[label]).inc();
+    }
+
+    pub fn record_two_fa_code_issued(&self) {
+        self.two_fa_codes_issued_total.inc();
+    }
+
+    pub fn record_two_fa_verification<T>(&self, result: &Result<T, AuthAPIError>) {
+        let label = match result {
+            Ok(_) => SUCCESS,
+            Err(e) => e.code().as_str(),
+        };
+        self.two_fa_codes_verified_total
+            .with_label_values(&[label])
+            .inc();
+    }
+
+    pub fn record_token_banned(&self) {
+        self.tokens_banned_total.inc();
+    }
+
+    pub fn record_email_send_failure(&self) {
+        self.email_send_failures_total.inc();
+    }
+
+    pub fn record_dependencies(&self, report: &[DependencyHealth]) {
+        for health in report {
+            self.dependency_up
+                .with_label_values(&[health.name])
+                .set(health.healthy as i64);
+        }
+    }
+
+    // Everything in the Prometheus text format
+    pub fn render(&self) -> prometheus::Result<String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+
+        String::from_utf8(buffer).map_err(|e| prometheus::Error::Msg(e.to_string()))
+    }
+}
+
+// Records the latency of every request, labelled with the route pattern rather than the path
+// so `/users/:email` is a single series
+pub async fn track_http_metrics(
+    State(metrics): State<Arc<Metrics>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let started = Instant::now();
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or(UNMATCHED_ROUTE.to_owned(), |path| path.as_str().to_owned());
+
+    let response = next.run(request).await;
+
+    metrics
+        .http_request_duration_seconds
+        .with_label_values(&[&method, &route, response.status().as_str()])
+        .observe(started.elapsed().as_secs_f64());
+
+    response
+}
+
+struct PgPoolCollector {
+    pool: PgPool,
+    connections: IntGaugeVec,
+}
+
+impl PgPoolCollector {
+    fn new(pool: PgPool) -> prometheus::Result<Self> {
+        let connections = IntGaugeVec::new(
+            Opts::new(
+                "auth_postgres_pool_connections",
+                "Open connections of the Postgres pool, by state",
+            ),
+            &["state"],
+        )?;
+
+        Ok(Self { pool, connections })
+    }
+}
+
+impl Collector for PgPoolCollector {
+    fn desc(&self) -> Vec<&Desc> {
+        self.connections.desc()
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        let size = i64::from(self.pool.size());
+        let idle = self.pool.num_idle() as i64;
+
+        self.connections.with_label_values(&["idle"]).set(idle);
+        self.connections
+            .with_label_values(&["in_use"])
+            .set(size - idle);
+
+        self.connections.collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{AuthAPIError, LoginAttemptId};
+    use std::time::Duration;
+
+    fn metrics() -> Metrics {
+        Metrics::new(Registry::new()).unwrap()
+    }
+
+    #[test]
+    fn renders_domain_counters_with_their_labels() {
+        let metrics = metrics();
+
+        metrics.record_signup();
+        metrics.record_login(&Ok(LoginOutcome::Authenticated("token".to_owned())));
+        metrics.record_login(&Ok(LoginOutcome::TwoFactorRequired(
+            LoginAttemptId::default(),
+        )));
+        metrics.record_login(&Err(AuthAPIError::IncorrectCredentials));
+        metrics.record_two_fa_verification(&Err::<(), _>(AuthAPIError::IncorrectCredentials));
+        metrics.record_token_banned();
+        metrics.record_dependencies(&[DependencyHealth {
+            name: "redis",
+            healthy: false,
+            latency: Duration::ZERO,
+        }]);
+
+        let output = metrics.render().unwrap();
+
+        assert!(output.contains("auth_signups_total 1"));
+        assert!(output.contains(r#"auth_logins_total{result="success"} 1"#));
+        assert!(output.contains(r#"auth_logins_total{result="two_fa_required"} 1"#));
+        assert!(output.contains(r#"auth_logins_total{result="incorrect_credentials"} 1"#));
+        assert!(output
+            .contains(r#"auth_two_fa_codes_verified_total{result="incorrect_credentials"} 1"#));
+        assert!(output.contains("auth_tokens_banned_total 1"));
+        assert!(output.contains("auth_email_send_failures_total 0"));
+        assert!(output.contains(r#"auth_dependency_up{dependency="redis"} 0"#));
+    }
+
+    #[test]
+    fn registries_are_independent() {
+        let first = metrics();
+        let second = metrics();
+
+        first.record_signup();
+
+        assert!(first.render().unwrap().contains("auth_signups_total 1"));
+        assert!(second.render().unwrap().contains("auth_signups_total 0"));
+    }
+}
@@ -1,3 +1,11 @@
 pub mod auth;
+pub mod auth_cookie;
 pub mod constants;
+pub mod cors;
+pub mod csrf;
+pub mod extractors;
+pub mod keyed_hash;
+pub mod metrics;
+pub mod redact;
+pub mod shutdown;
 pub mod tracing;
@@ -1,37 +1,241 @@
-use axum::{body::Body, extract::Request, response::Response};
+use axum::{
+    body::Body,
+    extract::{MatchedPath, Request},
+    http::{HeaderMap, HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
 use color_eyre::eyre::Result;
-use std::time::Duration;
+use opentelemetry::{
+    global,
+    propagation::Extractor,
+    trace::{TraceContextExt, TracerProvider as _},
+    Context, KeyValue,
+};
+use opentelemetry_sdk::{
+    propagation::TraceContextPropagator,
+    runtime,
+    trace::{self as sdktrace, TracerProvider},
+    Resource,
+};
+use std::{env, fmt as std_fmt, str::FromStr, time::Duration};
+use tonic::codegen::http as grpc_http;
 use tracing::{Level, Span};
 use tracing_error::ErrorLayer;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
 use tracing_subscriber::prelude::*;
 use tracing_subscriber::{fmt, EnvFilter};
 
-pub fn init_tracing() -> Result<()> {
-    let fmt_layer = fmt::layer().compact();
+use super::metrics::UNMATCHED_ROUTE;
+use super::redact::{RedactingMakeWriter, Redactor};
+
+pub const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+const SERVICE_NAME: &str = "auth-service";
+// Longer ids, or ids with other characters, are replaced rather than logged
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    // One human readable line per event
+    Compact,
+    // One JSON object per event, with the fields of the current span, for log aggregators
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "compact" => Ok(Self::Compact),
+            "json" => Ok(Self::Json),
+            other => Err(format!(
+                "Invalid log format: {}. Expected compact or json",
+                other
+            )),
+        }
+    }
+}
+
+impl std_fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut std_fmt::Formatter) -> std_fmt::Result {
+        match self {
+            Self::Compact => write!(f, "compact"),
+            Self::Json => write!(f, "json"),
+        }
+    }
+}
+
+// Logs to stdout, with emails, tokens and codes masked, and, when
+// `OTEL_EXPORTER_OTLP_ENDPOINT` is set, exports spans to that collector. Spans get trace ids
+// either way, so the W3C trace context of callers is kept.
+pub fn init_tracing(format: LogFormat) -> Result<()> {
+    let writer = RedactingMakeWriter::new(std::io::stdout, Redactor::new());
+    let fmt_layer = match format {
+        LogFormat::Compact => fmt::layer().compact().with_writer(writer).boxed(),
+        LogFormat::Json => fmt::layer()
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(false)
+            .with_writer(writer)
+            .boxed(),
+    };
 
     let filter_layer = EnvFilter::try_from_default_env().or_else(|_| EnvFilter::try_new("info"))?;
 
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    let otel_layer = tracing_opentelemetry::layer().with_tracer(init_tracer()?);
+
     tracing_subscriber::registry()
         .with(filter_layer)
         .with(fmt_layer)
+        .with(otel_layer)
         .with(ErrorLayer::default())
         .init();
 
     Ok(())
 }
 
-// Creates a new tracing span with a unique request ID for each incoming request.
-// This helps in tracking and correlating logs for individual requests.
+fn init_tracer() -> Result<sdktrace::Tracer> {
+    let service_name = env::var("OTEL_SERVICE_NAME").unwrap_or(SERVICE_NAME.to_owned());
+    let config = sdktrace::config()
+        .with_resource(Resource::new([KeyValue::new("service.name", service_name)]));
+
+    let export = env::var("OTEL_EXPORTER_OTLP_ENDPOINT").is_ok_and(|endpoint| !endpoint.is_empty());
+    if !export {
+        let provider = TracerProvider::builder().with_config(config).build();
+        let tracer = provider.tracer(SERVICE_NAME);
+        global::set_tracer_provider(provider);
+        return Ok(tracer);
+    }
+
+    // The exporter reads the endpoint, headers and timeout from the standard OTEL_* variables
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(opentelemetry_otlp::new_exporter().tonic())
+        .with_trace_config(config)
+        .install_batch(runtime::Tokio)?;
+
+    Ok(tracer)
+}
+
+// Sends the spans still buffered by the exporter, before the process exits
+pub fn shutdown_tracing() {
+    global::shutdown_tracer_provider();
+}
+
+// Creates a new tracing span for each incoming request, tagged with its request ID and
+// continuing the trace of the caller when it sent a `traceparent` header.
+// The span has the route rather than the URI, whose path may hold an email, e.g.
+// `/users/:email`: exported spans don't go through the `Redactor`.
 pub fn make_span_with_request_id(request: &Request<Body>) -> Span {
-    let request_id = uuid::Uuid::new_v4();
-    tracing::span!(
+    let request_id = request
+        .headers()
+        .get(X_REQUEST_ID)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or(UNMATCHED_ROUTE, MatchedPath::as_str);
+    let span = tracing::span!(
         Level::INFO,
         "[REQUEST]",
         method = tracing::field::display(request.method()),
-        uri = tracing::field::display(request.uri()),
+        route = tracing::field::display(route),
         version = tracing::field::debug(request.version()),
         request_id = tracing::field::display(request_id),
-    )
+        trace_id = tracing::field::Empty,
+    );
+    continue_trace(&span, extract_context(&HeaderExtractor(request.headers())));
+
+    span
+}
+
+// Same as `make_span_with_request_id`, for gRPC calls. Tonic still uses http 0.2.
+pub fn make_grpc_span(request: &grpc_http::Request<()>) -> Span {
+    let headers = request.headers();
+    let request_id = headers
+        .get(X_REQUEST_ID.as_str())
+        .and_then(|value| value.to_str().ok())
+        .filter(|request_id| is_valid_request_id(request_id))
+        .map_or_else(|| uuid::Uuid::new_v4().to_string(), str::to_owned);
+    let span = tracing::span!(
+        Level::INFO,
+        "[GRPC]",
+        uri = tracing::field::display(request.uri()),
+        request_id = tracing::field::display(request_id),
+        trace_id = tracing::field::Empty,
+    );
+    continue_trace(&span, extract_context(&GrpcHeaderExtractor(headers)));
+
+    span
+}
+
+fn continue_trace(span: &Span, parent: Context) {
+    span.set_parent(parent);
+    let trace_id = span.context().span().span_context().trace_id();
+    span.record("trace_id", tracing::field::display(trace_id));
+}
+
+fn extract_context(extractor: &dyn Extractor) -> Context {
+    global::get_text_map_propagator(|propagator| propagator.extract(extractor))
+}
+
+// Keeps the `X-Request-Id` sent by the caller, or a proxy in front of us, and generates one
+// otherwise. The id is echoed in the response, so clients can quote it in bug reports.
+pub async fn propagate_request_id(mut request: Request, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(X_REQUEST_ID)
+        .filter(|value| value.to_str().is_ok_and(is_valid_request_id))
+        .cloned()
+        .unwrap_or_else(new_request_id);
+    request
+        .headers_mut()
+        .insert(X_REQUEST_ID, request_id.clone());
+
+    let mut response = next.run(request).await;
+    response.headers_mut().insert(X_REQUEST_ID, request_id);
+
+    response
+}
+
+fn new_request_id() -> HeaderValue {
+    HeaderValue::from_str(&uuid::Uuid::new_v4().to_string()).expect("UUIDs are valid headers")
+}
+
+fn is_valid_request_id(request_id: &str) -> bool {
+    !request_id.is_empty()
+        && request_id.len() <= MAX_REQUEST_ID_LENGTH
+        && request_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(HeaderName::as_str).collect()
+    }
+}
+
+struct GrpcHeaderExtractor<'a>(&'a grpc_http::HeaderMap);
+
+impl Extractor for GrpcHeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(grpc_http::HeaderName::as_str).collect()
+    }
 }
 
 // Logs an event indicating the start of a request.
@@ -65,3 +269,85 @@ pub fn on_response(response: &Response, latency: Duration, _span: &Span) {
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::delete, Router};
+    use futures::future::BoxFuture;
+    use opentelemetry::propagation::TextMapPropagator;
+    use opentelemetry_sdk::export::trace::{ExportResult, SpanData, SpanExporter};
+    use std::sync::{Arc, Mutex};
+    use tower::ServiceExt;
+    use tower_http::trace::TraceLayer;
+
+    // Keeps what would be sent to the collector
+    #[derive(Debug, Clone, Default)]
+    struct CapturingExporter(Arc<Mutex<Vec<SpanData>>>);
+
+    impl SpanExporter for CapturingExporter {
+        fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+            self.0.lock().unwrap().extend(batch);
+            Box::pin(std::future::ready(Ok(())))
+        }
+    }
+
+    #[tokio::test]
+    async fn exports_the_route_rather_than_the_path() {
+        let exporter = CapturingExporter::default();
+        let provider = TracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME)));
+        let app = Router::new()
+            .route("/users/:email", delete(|| async {}))
+            .layer(TraceLayer::new_for_http().make_span_with(make_span_with_request_id));
+        let request = Request::delete("/users/user@example.com")
+            .body(Body::empty())
+            .unwrap();
+
+        let guard = tracing::subscriber::set_default(subscriber);
+        drop(app.oneshot(request).await.unwrap());
+        drop(guard);
+        provider.force_flush();
+
+        let spans = exporter.0.lock().unwrap();
+        let span = spans
+            .iter()
+            .find(|span| span.name == "[REQUEST]")
+            .expect("The request span wasn't exported");
+        let attributes = format!("{:?}", span.attributes);
+        assert!(attributes.contains("/users/:email"));
+        assert!(!attributes.contains("user@example.com"));
+    }
+
+    #[test]
+    fn extracts_the_w3c_trace_context() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "traceparent",
+            HeaderValue::from_static("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"),
+        );
+
+        let context = TraceContextPropagator::new().extract(&HeaderExtractor(&headers));
+        let span_context = context.span().span_context().clone();
+
+        assert!(span_context.is_remote());
+        assert_eq!(
+            span_context.trace_id().to_string(),
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+        assert_eq!(span_context.span_id().to_string(), "00f067aa0ba902b7");
+    }
+
+    #[test]
+    fn accepts_only_short_printable_request_ids() {
+        assert!(is_valid_request_id("3f2b8c1e-7d4a-4c8e-9b1a-2e5f6a7b8c9d"));
+        assert!(is_valid_request_id("proxy.req_42"));
+        assert!(!is_valid_request_id(""));
+        assert!(!is_valid_request_id("id with spaces"));
+        assert!(!is_valid_request_id("id\nforged log line"));
+        assert!(!is_valid_request_id(&"a".repeat(MAX_REQUEST_ID_LENGTH + 1)));
+    }
+}
@@ -0,0 +1,161 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::{
+    domain::{
+        data_stores::{UserStore, UserStoreError},
+        Email, Password, User,
+    },
+    services::postgres_user_store::PostgresUserStore,
+};
+use futures::future::join_all;
+use secrecy::ExposeSecret;
+use std::{sync::Arc, time::Duration};
+use tokio::sync::Notify;
+
+// Holds the signup of one email inside `add_user` until the test releases it
+struct HeldUserStore {
+    inner: PostgresUserStore,
+    held_email: String,
+    entered: Arc<Notify>,
+    release: Arc<Notify>,
+}
+
+#[async_trait::async_trait]
+impl UserStore for HeldUserStore {
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError> {
+        if user.email.as_ref().expose_secret() == &self.held_email {
+            self.entered.notify_one();
+            self.release.notified().await;
+        }
+        self.inner.add_user(user).await
+    }
+
+    async fn delete_user(&self, user: User) -> Result<(), UserStoreError> {
+        self.inner.delete_user(user).await
+    }
+
+    async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
+        self.inner.get_user(email).await
+    }
+
+    async fn validate_user(
+        &self,
+        email: &Email,
+        password: &Password,
+    ) -> Result<(), UserStoreError> {
+        self.inner.validate_user(email, password).await
+    }
+}
+
+fn signup_body(email: &str) -> serde_json::Value {
+    serde_json::json!({
+        "email": email,
+        "password": "abcDEF123",
+        "requires2FA": false,
+        "recaptcha": "recaptcha",
+    })
+}
+
+#[tokio::test]
+async fn logins_and_signups_should_not_wait_for_a_pending_signup() {
+    let held_email = get_random_email();
+    let entered = Arc::new(Notify::new());
+    let release = Arc::new(Notify::new());
+    let mut app = TestApp::with_user_store({
+        let (held_email, entered, release) = (held_email.clone(), entered.clone(), release.clone());
+        move |pg_pool| {
+            Arc::new(HeldUserStore {
+                inner: PostgresUserStore::new(pg_pool),
+                held_email,
+                entered,
+                release,
+            })
+        }
+    })
+    .await;
+
+    let email = get_random_email();
+    let response = app.post_signup(&signup_body(&email)).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let held_body = signup_body(&held_email);
+    let held_signup = app.post_signup(&held_body);
+    let meanwhile = async {
+        entered.notified().await;
+
+        let login = tokio::time::timeout(
+            Duration::from_secs(5),
+            app.post_login(&serde_json::json!({
+                "email": email,
+                "password": "abcDEF123",
+            })),
+        )
+        .await
+        .expect("Login waited for the pending signup");
+        let signup = tokio::time::timeout(
+            Duration::from_secs(5),
+            app.post_signup(&signup_body(&get_random_email())),
+        )
+        .await
+        .expect("Signup waited for the pending signup");
+
+        release.notify_one();
+        (login, signup)
+    };
+
+    let (held_signup, (login, signup)) = tokio::join!(held_signup, meanwhile);
+
+    assert_eq!(login.status().as_u16(), 200);
+    assert_eq!(signup.status().as_u16(), 201);
+    assert_eq!(held_signup.status().as_u16(), 201);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn concurrent_signups_should_all_succeed() {
+    let mut app = TestApp::new().await;
+
+    let emails: Vec<String> = (0..20).map(|_| get_random_email()).collect();
+    let signup_bodies: Vec<_> = emails.iter().map(|email| signup_body(email)).collect();
+    let responses = join_all(signup_bodies.iter().map(|body| app.post_signup(body))).await;
+
+    for response in responses {
+        assert_eq!(response.status().as_u16(), 201);
+    }
+
+    let login_bodies: Vec<_> = emails
+        .iter()
+        .map(|email| {
+            serde_json::json!({
+                "email": email,
+                "password": "abcDEF123",
+            })
+        })
+        .collect();
+    let responses = join_all(login_bodies.iter().map(|body| app.post_login(body))).await;
+
+    for response in responses {
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn concurrent_signups_for_the_same_email_should_create_one_user() {
+    let mut app = TestApp::new().await;
+    let body = signup_body(&get_random_email());
+
+    let responses = join_all((0..10).map(|_| app.post_signup(&body))).await;
+    let statuses: Vec<u16> = responses
+        .iter()
+        .map(|response| response.status().as_u16())
+        .collect();
+
+    assert_eq!(statuses.iter().filter(|status| **status == 201).count(), 1);
+    assert!(statuses
+        .iter()
+        .all(|status| *status == 201 || *status == 409));
+
+    app.clean_up().await;
+}
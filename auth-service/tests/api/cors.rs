@@ -0,0 +1,117 @@
+use crate::helpers::TestApp;
+use auth_service::{domain::path::Paths, utils::constants::CSRF_HEADER_NAME};
+use axum::http::Method;
+
+fn header<'a>(response: &'a reqwest::Response, name: &str) -> Option<&'a str> {
+    response
+        .headers()
+        .get(name)
+        .map(|value| value.to_str().expect("Invalid header value"))
+}
+
+#[tokio::test]
+async fn preflight_from_allowed_origin_lists_configured_methods_and_headers() {
+    let mut app = TestApp::new().await;
+
+    let users_path = format!("{}/user@example.com", Paths::Users.as_str());
+    let response = app
+        .preflight(&users_path, "http://localhost:8000", Method::DELETE)
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        header(&response, "access-control-allow-origin"),
+        Some("http://localhost:8000")
+    );
+    assert_eq!(
+        header(&response, "access-control-allow-credentials"),
+        Some("true")
+    );
+    assert_eq!(
+        header(&response, "access-control-allow-methods"),
+        Some("GET,POST,DELETE")
+    );
+    let allowed_headers = header(&response, "access-control-allow-headers").unwrap();
+    assert!(allowed_headers.contains("content-type"));
+    assert!(allowed_headers.contains(CSRF_HEADER_NAME));
+    assert_eq!(header(&response, "access-control-max-age"), Some("600"));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn preflight_from_wildcard_subdomain_is_allowed() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .preflight(
+            Paths::Login.as_str(),
+            "https://app.example.com",
+            Method::POST,
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        header(&response, "access-control-allow-origin"),
+        Some("https://app.example.com")
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn preflight_from_other_origins_is_not_allowed() {
+    let mut app = TestApp::new().await;
+
+    for origin in [
+        "https://example.com",
+        "http://app.example.com",
+        "https://app.example.com.evil.com",
+        "http://localhost:3001",
+    ] {
+        let response = app
+            .preflight(Paths::Login.as_str(), origin, Method::POST)
+            .await;
+
+        assert_eq!(
+            header(&response, "access-control-allow-origin"),
+            None,
+            "{}",
+            origin
+        );
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn simple_requests_echo_only_allowed_origins() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .http_client
+        .get(format!("{}{}", &app.address, Paths::Csrf.as_str()))
+        .header("Origin", "https://app.example.com")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        header(&response, "access-control-allow-origin"),
+        Some("https://app.example.com")
+    );
+
+    let response = app
+        .http_client
+        .get(format!("{}{}", &app.address, Paths::Csrf.as_str()))
+        .header("Origin", "https://evil.com")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(header(&response, "access-control-allow-origin"), None);
+
+    app.clean_up().await;
+}
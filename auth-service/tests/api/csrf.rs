@@ -0,0 +1,165 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_domain::ErrorCode;
+use auth_service::{
+    domain::path::Paths,
+    routes::{CsrfResponse, TokenResponse},
+    utils::constants::CSRF_COOKIE_NAME,
+    ErrorResponse,
+};
+
+async fn signup_and_login(app: &TestApp, email: &str) -> String {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "abcDEF123",
+        "requires2FA": false,
+        "recaptcha": "recaptcha",
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "abcDEF123",
+        "returnToken": true,
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse")
+        .token
+}
+
+async fn assert_invalid_csrf_token(response: reqwest::Response) {
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .code,
+        ErrorCode::InvalidCsrfToken
+    );
+}
+
+#[tokio::test]
+async fn should_return_csrf_token_matching_its_cookie() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_csrf().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let csrf_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == CSRF_COOKIE_NAME)
+        .expect("No CSRF cookie found");
+    assert_eq!(csrf_cookie.value(), app.csrf_token);
+    assert!(csrf_cookie.http_only());
+
+    let body = response
+        .json::<CsrfResponse>()
+        .await
+        .expect("Could not deserialize response body to CsrfResponse");
+    assert_eq!(
+        body.csrf_token, app.csrf_token,
+        "An existing token should be kept"
+    );
+
+    // Clean up database
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_403_if_cookie_authenticated_request_has_no_csrf_token() {
+    let mut app = TestApp::new().await;
+    signup_and_login(&app, &get_random_email()).await;
+
+    assert_invalid_csrf_token(app.post_logout_with_csrf_token(None).await).await;
+
+    // The session wasn't touched
+    assert_eq!(app.get_session().await.status().as_u16(), 200);
+
+    // Clean up database
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_403_if_csrf_token_does_not_match_cookie() {
+    let mut app = TestApp::new().await;
+    signup_and_login(&app, &get_random_email()).await;
+
+    // A token obtained by another client, as an attacker would have
+    let other_token = reqwest::Client::new()
+        .get(format!("{}{}", &app.address, Paths::Csrf.as_str()))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json::<CsrfResponse>()
+        .await
+        .expect("Could not deserialize response body to CsrfResponse")
+        .csrf_token;
+    assert_ne!(other_token, app.csrf_token);
+
+    assert_invalid_csrf_token(app.post_logout_with_csrf_token(Some(&other_token)).await).await;
+    assert_eq!(app.get_session().await.status().as_u16(), 200);
+
+    // Clean up database
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_accept_cookie_authenticated_request_with_csrf_token() {
+    let mut app = TestApp::new().await;
+    signup_and_login(&app, &get_random_email()).await;
+
+    let response = app.post_logout_with_csrf_token(Some(&app.csrf_token)).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Clean up database
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_require_csrf_token_for_bearer_authenticated_requests() {
+    let mut app = TestApp::new().await;
+    let token = signup_and_login(&app, &get_random_email()).await;
+
+    // The cookie jar still sends the auth cookie, the Bearer header exempts the request
+    let response = app
+        .http_client
+        .post(format!("{}{}", &app.address, Paths::Logout.as_str()))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Clean up database
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_require_csrf_token_without_auth_cookie() {
+    let mut app = TestApp::new().await;
+
+    let signup_body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "abcDEF123",
+        "requires2FA": false,
+        "recaptcha": "recaptcha",
+    });
+    let response = reqwest::Client::new()
+        .post(format!("{}{}", &app.address, Paths::Signup.as_str()))
+        .json(&signup_body)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 201);
+
+    // Clean up database
+    app.clean_up().await;
+}
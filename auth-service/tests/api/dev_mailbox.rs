@@ -0,0 +1,59 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::{
+    domain::{Email, EmailClient, EmailMessage},
+    services::file_email_client::FileEmailClient,
+};
+use secrecy::Secret;
+
+#[tokio::test]
+async fn should_list_and_show_emails_written_to_local_mailbox() {
+    let mut app = TestApp::new().await;
+
+    let mailbox = app.settings.email.mailbox.clone();
+    let sender = Email::parse(Secret::new("no-reply@example.com".to_owned())).unwrap();
+    let recipient = Email::parse(Secret::new(get_random_email())).unwrap();
+    let subject = format!("Verify your email {}", uuid::Uuid::new_v4());
+
+    FileEmailClient::new(sender, mailbox.clone())
+        .send_email(
+            &recipient,
+            &EmailMessage {
+                subject: subject.clone(),
+                html_body: "<p>Verify your email</p>".to_owned(),
+                text_body: "Open https://example.com/verify?token=abc".to_owned(),
+            },
+        )
+        .await
+        .expect("Failed to write email to mailbox");
+
+    let response = app.get_dev_mailbox(None).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.text().await.unwrap().contains(&subject));
+
+    let id = mailbox
+        .list()
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|message| message.subject == subject)
+        .expect("Email not found in mailbox")
+        .id;
+
+    let response = app.get_dev_mailbox(Some(&id)).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.text().await.unwrap().contains("verify?token=abc"));
+
+    // Clean up database
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_404_for_unknown_email() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_dev_mailbox(Some("unknown.eml")).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    // Clean up database
+    app.clean_up().await;
+}
@@ -0,0 +1,41 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::{domain::OutboxStatus, routes::TwoFactorAuthResponse};
+
+#[tokio::test]
+async fn login_with_2fa_should_record_sent_email_in_outbox() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "abcDEF123",
+        "requires2FA": true,
+        "recaptcha": "recaptcha",
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "abcDEF123",
+    });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    let status = app
+        .email_outbox
+        .get_status(&format!("two_fa_code:{}", login_attempt_id))
+        .await
+        .expect("Failed to read outbox status");
+
+    assert_eq!(status, Some(OutboxStatus::Sent));
+
+    // Clean up database
+    app.clean_up().await;
+}
@@ -0,0 +1,243 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_proto::{
+    auth_client::AuthClient, login_response, DeleteUserRequest, LoginRequest, LogoutRequest,
+    SignupRequest, StatusCode, Verify2FaRequest, VerifyTokenRequest,
+};
+use tonic::{transport::Channel, Code};
+
+fn signup_request(email: &str, requires_2fa: bool) -> SignupRequest {
+    SignupRequest {
+        email: email.to_owned(),
+        password: "abcDEF123".to_owned(),
+        requires_2fa,
+        recaptcha: "recaptcha".to_owned(),
+    }
+}
+
+fn login_request(email: &str) -> LoginRequest {
+    LoginRequest {
+        email: email.to_owned(),
+        password: "abcDEF123".to_owned(),
+        locale: None,
+    }
+}
+
+async fn login_token(client: &mut AuthClient<Channel>, email: &str) -> String {
+    let response = client
+        .login(login_request(email))
+        .await
+        .expect("Login failed")
+        .into_inner();
+
+    match response.result {
+        Some(login_response::Result::Token(token)) => token,
+        other => panic!("Expected a token, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn grpc_login_returns_token_that_can_be_verified_and_revoked() {
+    let mut app = TestApp::new().await;
+    let mut client = app.grpc_client().await;
+    let random_email = get_random_email();
+
+    client
+        .signup(signup_request(&random_email, false))
+        .await
+        .expect("Signup failed");
+
+    let response = client
+        .login(login_request(&random_email))
+        .await
+        .expect("Login failed")
+        .into_inner();
+
+    let token = match response.result {
+        Some(login_response::Result::Token(token)) => token,
+        other => panic!("Expected a token, got {:?}", other),
+    };
+
+    let response = client
+        .verify_token(VerifyTokenRequest {
+            token: token.clone(),
+        })
+        .await
+        .expect("Token should be valid")
+        .into_inner();
+    assert_eq!(response.status(), StatusCode::Ok);
+
+    client
+        .logout(LogoutRequest {
+            token: token.clone(),
+        })
+        .await
+        .expect("Logout failed");
+
+    let status = client
+        .verify_token(VerifyTokenRequest { token })
+        .await
+        .expect_err("Token should be banned after logout");
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    // Clean up database
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn grpc_login_with_2fa_requires_code_sent_by_email() {
+    let mut app = TestApp::new().await;
+    let mut client = app.grpc_client().await;
+    let random_email = get_random_email();
+
+    client
+        .signup(signup_request(&random_email, true))
+        .await
+        .expect("Signup failed");
+
+    let response = client
+        .login(login_request(&random_email))
+        .await
+        .expect("Login failed")
+        .into_inner();
+
+    let login_attempt_id = match response.result {
+        Some(login_response::Result::TwoFactorAuth(two_factor_auth)) => {
+            two_factor_auth.login_attempt_id
+        }
+        other => panic!("Expected 2FA to be required, got {:?}", other),
+    };
+
+    let status = client
+        .verify2_fa(Verify2FaRequest {
+            email: random_email.clone(),
+            login_attempt_id: login_attempt_id.clone(),
+            two_fa_code: "000000".to_owned(),
+        })
+        .await
+        .expect_err("Wrong code should be rejected");
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    let two_fa_code = app
+        .last_email_to(&random_email)
+        .extract_code()
+        .expect("No 2FA code found in email");
+
+    let response = client
+        .verify2_fa(Verify2FaRequest {
+            email: random_email.clone(),
+            login_attempt_id,
+            two_fa_code,
+        })
+        .await
+        .expect("Verify 2FA failed")
+        .into_inner();
+    assert!(!response.token.is_empty());
+
+    // Clean up database
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn grpc_errors_map_to_status_codes() {
+    let mut app = TestApp::new().await;
+    let mut client = app.grpc_client().await;
+    let random_email = get_random_email();
+
+    let status = client
+        .signup(signup_request("not_an_email", false))
+        .await
+        .expect_err("Invalid email should be rejected");
+    assert_eq!(status.code(), Code::InvalidArgument);
+
+    client
+        .signup(signup_request(&random_email, false))
+        .await
+        .expect("Signup failed");
+
+    let status = client
+        .signup(signup_request(&random_email, false))
+        .await
+        .expect_err("Duplicated user should be rejected");
+    assert_eq!(status.code(), Code::AlreadyExists);
+
+    let status = client
+        .login(LoginRequest {
+            password: "wrongPASS123".to_owned(),
+            ..login_request(&random_email)
+        })
+        .await
+        .expect_err("Wrong password should be rejected");
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    let status = client
+        .logout(LogoutRequest {
+            token: String::new(),
+        })
+        .await
+        .expect_err("Missing token should be rejected");
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    // Clean up database
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn grpc_delete_user_removes_account() {
+    let mut app = TestApp::new().await;
+    let mut client = app.grpc_client().await;
+    let random_email = get_random_email();
+    let other_email = get_random_email();
+
+    for email in [&random_email, &other_email] {
+        client
+            .signup(signup_request(email, false))
+            .await
+            .expect("Signup failed");
+    }
+    let token = login_token(&mut client, &random_email).await;
+    let other_token = login_token(&mut client, &other_email).await;
+
+    let status = client
+        .delete_user(DeleteUserRequest {
+            email: random_email.clone(),
+            token: String::new(),
+        })
+        .await
+        .expect_err("Deleting a user without a token should fail");
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    let status = client
+        .delete_user(DeleteUserRequest {
+            email: random_email.clone(),
+            token: other_token,
+        })
+        .await
+        .expect_err("Deleting another user should fail");
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    client
+        .delete_user(DeleteUserRequest {
+            email: random_email.clone(),
+            token: token.clone(),
+        })
+        .await
+        .expect("Delete user failed");
+
+    let status = client
+        .login(login_request(&random_email))
+        .await
+        .expect_err("Deleted user should not be able to log in");
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    let status = client
+        .delete_user(DeleteUserRequest {
+            email: random_email,
+            token,
+        })
+        .await
+        .expect_err("Deleting an unknown user should fail");
+    assert_eq!(status.code(), Code::InvalidArgument);
+
+    // Clean up database
+    app.clean_up().await;
+}
@@ -0,0 +1,46 @@
+use crate::helpers::TestApp;
+use auth_service::{
+    domain::path::Paths,
+    routes::{HealthStatus, LivenessResponse, ReadinessResponse},
+};
+
+#[tokio::test]
+async fn live_should_return_200() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_health(Paths::HealthLive).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response
+            .json::<LivenessResponse>()
+            .await
+            .expect("Could not deserialize response body to LivenessResponse")
+            .status,
+        HealthStatus::Ok
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn ready_should_return_200_with_every_dependency_up() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_health(Paths::HealthReady).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response
+        .json::<ReadinessResponse>()
+        .await
+        .expect("Could not deserialize response body to ReadinessResponse");
+    assert_eq!(body.status, HealthStatus::Ok);
+
+    let dependencies: Vec<_> = body.checks.keys().map(String::as_str).collect();
+    assert_eq!(dependencies, app.dependencies());
+    assert!(body
+        .checks
+        .values()
+        .all(|check| check.status == HealthStatus::Ok));
+
+    app.clean_up().await;
+}
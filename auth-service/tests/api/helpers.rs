@@ -1,79 +1,122 @@
+use auth_proto::auth_client::AuthClient;
 use auth_service::{
-    app_state::{AppState, BannedTokenStoreType, EmailClientType, TwoFACodeStoreType},
-    domain::path::Paths,
-    get_postgres_pool,
+    app_state::{
+        AppState, BannedTokenStoreType, EmailOutboxStoreType, HealthChecksType, TwoFACodeStoreType,
+        UserStoreType,
+    },
+    domain::{environment::Environment, path::Paths, Email, EmailDelivery, HealthCheck},
+    get_postgres_pool, get_redis_connection,
+    routes::CsrfResponse,
     services::{
-        data_stores::{RedisBannedTokenStore, RedisTwoFACodeStore},
-        mock_email_client::MockEmailClient,
+        capturing_email_client::{CapturingEmailClient, SentEmail},
+        data_stores::{
+            PostgresBannedTokenStore, PostgresEmailOutboxStore, PostgresTwoFACodeStore,
+            RedisBannedTokenStore, RedisConnection, RedisTwoFACodeStore,
+        },
+        file_email_client::{FileMailbox, MailboxFormat},
+        health_checks::{PostgresHealthCheck, RedisHealthCheck},
         postgres_user_store::PostgresUserStore,
     },
-    utils::constants::{self, test},
+    settings::{
+        ApplicationSettings, DatabaseSettings, EmailClientSettings, EmailSettings, HealthSettings,
+        JwtSettings, LoggingSettings, RecaptchaSettings, RedisSettings, RedisTopology, Settings,
+        ShutdownSettings, StoreBackend, StoresSettings,
+    },
+    utils::{
+        auth_cookie::AuthCookieSettings,
+        constants,
+        cors::{AllowedOrigin, CorsSettings},
+        keyed_hash::KeyedHasher,
+        metrics::Metrics,
+        tracing::LogFormat,
+    },
     Application,
 };
-use redis::{Client as RedisClient, RedisResult};
+use axum::http::{
+    header::{AUTHORIZATION, CONTENT_TYPE},
+    HeaderName, Method,
+};
+use prometheus::Registry;
 use reqwest::cookie::Jar;
-use secrecy::{ExposeSecret, Secret};
+use secrecy::Secret;
 use sqlx::{
     postgres::{PgConnectOptions, PgPoolOptions},
     Connection, Executor, PgConnection, PgPool,
 };
-use std::{str::FromStr, sync::Arc};
+use std::{str::FromStr, sync::Arc, time::Duration};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
 pub struct TestApp {
     pub address: String,
+    pub grpc_address: String,
+    pub settings: Arc<Settings>,
+    // Registry of this app only, tests can read it without the other apps interfering
+    pub metrics: Arc<Metrics>,
     pub cookie_jar: Arc<Jar>,
     pub banned_token_store: BannedTokenStoreType,
     pub http_client: reqwest::Client,
     pub two_fa_code_store: TwoFACodeStoreType,
-    pub email_client: EmailClientType,
+    // Sent in the CSRF header by the helpers using the cookie jar, like the frontend does
+    pub csrf_token: String,
+    pub email_client: CapturingEmailClient,
+    pub email_outbox: EmailOutboxStoreType,
     pub database_name: String,
     pub clean_up_called: bool,
+    // Stands in for SIGTERM
+    pub stop: CancellationToken,
+    pub server: Option<JoinHandle<Result<(), std::io::Error>>>,
 }
 
 impl TestApp {
     pub async fn new() -> Self {
-        /*
-         * How to test recaptcha:
-         * https://developers.google.com/recaptcha/docs/faq#id-like-to-run-automated-tests-with-recaptcha.-what-should-i-do
-         */
-        std::env::set_var(
-            constants::env::RECAPTCHA_SECRET_ENV_VAR,
-            "6LeIxAcTAAAAAGG-vFI1TnRWxMZNFuojJ4WifJWe",
-        );
-        std::env::set_var(constants::env::DROPLET_IP_ENV_VAR, "127.0.0.1");
-        std::env::set_var(constants::env::JWT_SECRET_ENV_VAR, "foobar");
-        std::env::set_var(constants::env::BASE_PATH_ENV_VAR, "http://localhost");
+        Self::with_user_store(|pg_pool| Arc::new(PostgresUserStore::new(pg_pool))).await
+    }
 
+    // Lets a test wrap the user store, e.g. to hold requests inside it
+    pub async fn with_user_store<F>(make_user_store: F) -> Self
+    where
+        F: FnOnce(PgPool) -> UserStoreType,
+    {
         // We are creating a new database for each test case, and we need to ensure each database has a unique name!
         let database_name = Uuid::new_v4().to_string();
+        let settings = Arc::new(test_settings(&database_name));
         let pg_pool = configure_postgresql(&database_name).await;
-        let redis_connection = Arc::new(tokio::sync::RwLock::new(configure_redis()));
+        let user_store = make_user_store(pg_pool.clone());
+        let (banned_token_store, two_fa_code_store, redis_connection) =
+            configure_stores(&settings, &pg_pool).await;
+        let email_client = CapturingEmailClient::default();
+        let email_outbox = Arc::new(PostgresEmailOutboxStore::new(pg_pool.clone()));
+        let mut health_checks: Vec<Arc<dyn HealthCheck + Send + Sync>> =
+            vec![Arc::new(PostgresHealthCheck::new(pg_pool))];
+        if let Some(redis_connection) = redis_connection {
+            health_checks.push(Arc::new(RedisHealthCheck::new(redis_connection)));
+        }
+        let health_checks: HealthChecksType = Arc::new(health_checks);
 
-        let user_store = Arc::new(tokio::sync::RwLock::new(PostgresUserStore::new(pg_pool)));
-        let banned_token_store = Arc::new(tokio::sync::RwLock::new(RedisBannedTokenStore::new(
-            redis_connection.clone(),
-        )));
-        let two_fa_code_store = Arc::new(tokio::sync::RwLock::new(RedisTwoFACodeStore::new(
-            redis_connection,
-        )));
-        let email_client = Arc::new(MockEmailClient);
+        let metrics = Arc::new(Metrics::new(Registry::new()).expect("Failed to register metrics"));
 
         let app_state = AppState::new(
             user_store,
             banned_token_store.clone(),
             two_fa_code_store.clone(),
-            email_client.clone(),
+            Arc::new(email_client.clone()),
+            email_outbox.clone(),
+            health_checks,
+            metrics.clone(),
+            settings.clone(),
         );
 
-        let app = Application::build(app_state, test::APP_ADDRESS)
+        let app = Application::build(app_state)
             .await
             .expect("Failed to build app");
 
         let address = format!("http://{}", app.address.clone());
+        let grpc_address = format!("http://{}", app.grpc_address.clone());
 
-        #[allow(clippy::let_underscore_future)]
-        let _ = tokio::spawn(app.run());
+        let stop = CancellationToken::new();
+        let server = tokio::spawn(app.run_until(stop.clone().cancelled_owned()));
 
         let cookie_jar = Arc::new(Jar::default());
         let http_client = reqwest::Client::builder()
@@ -81,21 +124,38 @@ impl TestApp {
             .build()
             .unwrap();
 
+        let csrf_token = http_client
+            .get(format!("{}{}", &address, Paths::Csrf.as_str()))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .json::<CsrfResponse>()
+            .await
+            .expect("Failed to get a CSRF token")
+            .csrf_token;
+
         Self {
             address,
+            grpc_address,
+            settings,
+            metrics,
             cookie_jar,
             banned_token_store,
             http_client,
             two_fa_code_store,
+            csrf_token,
             email_client,
+            email_outbox,
             database_name,
             clean_up_called: false,
+            stop,
+            server: Some(server),
         }
     }
 
     pub async fn get_root(&self) -> reqwest::Response {
         self.http_client
-            .get(&format!("{}/", &self.address))
+            .get(format!("{}/", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -106,7 +166,8 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}{}", &self.address, Paths::Signup.as_str()))
+            .post(format!("{}{}", &self.address, Paths::Signup.as_str()))
+            .header(constants::CSRF_HEADER_NAME, &self.csrf_token)
             .json(body)
             .send()
             .await
@@ -118,7 +179,8 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}{}", &self.address, Paths::Login.as_str()))
+            .post(format!("{}{}", &self.address, Paths::Login.as_str()))
+            .header(constants::CSRF_HEADER_NAME, &self.csrf_token)
             .json(body)
             .send()
             .await
@@ -126,8 +188,113 @@ impl TestApp {
     }
 
     pub async fn post_logout(&self) -> reqwest::Response {
+        self.post_logout_with_csrf_token(Some(&self.csrf_token))
+            .await
+    }
+
+    // Sends the cookies from the jar, with the given token in the CSRF header
+    pub async fn post_logout_with_csrf_token(&self, csrf_token: Option<&str>) -> reqwest::Response {
+        let mut request =
+            self.http_client
+                .post(format!("{}{}", &self.address, Paths::Logout.as_str()));
+        if let Some(csrf_token) = csrf_token {
+            request = request.header(constants::CSRF_HEADER_NAME, csrf_token);
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    // CORS preflight a browser on `origin` sends before calling `path` with `method`
+    pub async fn preflight(&self, path: &str, origin: &str, method: Method) -> reqwest::Response {
+        self.http_client
+            .request(Method::OPTIONS, format!("{}{}", &self.address, path))
+            .header("Origin", origin)
+            .header("Access-Control-Request-Method", method.as_str())
+            .header(
+                "Access-Control-Request-Headers",
+                format!("content-type,{}", constants::CSRF_HEADER_NAME),
+            )
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_metrics(&self) -> String {
+        self.http_client
+            .get(format!("{}{}", &self.address, Paths::Metrics.as_str()))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .expect("Failed to read metrics")
+    }
+
+    // What the readiness probe checks, Redis only backs the stores with `StoreBackend::Redis`
+    pub fn dependencies(&self) -> Vec<&'static str> {
+        match self.settings.stores.backend {
+            StoreBackend::Redis(_) => vec!["postgres", "redis"],
+            StoreBackend::Postgres => vec!["postgres"],
+        }
+    }
+
+    pub async fn get_health(&self, path: Paths) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}{}", &self.address, path.as_str()))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_health_with_request_id(&self, request_id: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}{}", &self.address, Paths::HealthLive.as_str()))
+            .header("x-request-id", request_id)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_csrf(&self) -> reqwest::Response {
         self.http_client
+            .get(format!("{}{}", &self.address, Paths::Csrf.as_str()))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // Sends the token as `Authorization: Bearer` from a client without the cookie jar, like
+    // mobile and CLI clients would
+    pub async fn post_logout_with_bearer(&self, token: &str) -> reqwest::Response {
+        reqwest::Client::new()
             .post(format!("{}{}", &self.address, Paths::Logout.as_str()))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_session(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}{}", &self.address, Paths::Session.as_str()))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_session_with_bearer(&self, token: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}{}", &self.address, Paths::Session.as_str()))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_verify_token_with_bearer(&self, token: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}{}", &self.address, Paths::VerifyToken.as_str()))
+            .bearer_auth(token)
             .send()
             .await
             .expect("Failed to execute request.")
@@ -139,6 +306,7 @@ impl TestApp {
     {
         self.http_client
             .post(format!("{}{}", &self.address, Paths::Verify2FA.as_str()))
+            .header(constants::CSRF_HEADER_NAME, &self.csrf_token)
             .json(body)
             .send()
             .await
@@ -151,6 +319,7 @@ impl TestApp {
     {
         self.http_client
             .post(format!("{}{}", &self.address, Paths::VerifyToken.as_str()))
+            .header(constants::CSRF_HEADER_NAME, &self.csrf_token)
             .json(body)
             .send()
             .await
@@ -159,12 +328,41 @@ impl TestApp {
 
     pub async fn delete_user(&self, email: String) -> reqwest::Response {
         self.http_client
-            .delete(&format!(
+            .delete(format!(
                 "{}{}/{}",
                 &self.address,
                 Paths::Users.as_str(),
                 email
             ))
+            .header(constants::CSRF_HEADER_NAME, &self.csrf_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn grpc_client(&self) -> AuthClient<tonic::transport::Channel> {
+        AuthClient::connect(self.grpc_address.clone())
+            .await
+            .expect("Failed to connect to gRPC server.")
+    }
+
+    // Last email the app sent to `email`, as the user would receive it.
+    pub fn last_email_to(&self, email: &str) -> SentEmail {
+        let email = Email::parse(Secret::new(email.to_owned())).expect("Invalid email address");
+
+        self.email_client
+            .last_email_to(&email)
+            .expect("No email was sent to this address")
+    }
+
+    pub async fn get_dev_mailbox(&self, id: Option<&str>) -> reqwest::Response {
+        let path = match id {
+            Some(id) => format!("{}/{}", Paths::DevMailbox.as_str(), id),
+            None => Paths::DevMailbox.as_str().to_owned(),
+        };
+
+        self.http_client
+            .get(format!("{}{}", &self.address, path))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -185,22 +383,147 @@ impl Drop for TestApp {
     }
 }
 
-fn configure_redis() -> redis::Connection {
-    let redis_hostname = constants::REDIS_HOST_NAME.to_owned();
+// Config of an app on random local ports, using the database created for the test
+pub fn test_settings(database_name: &str) -> Settings {
+    Settings {
+        environment: Environment::Local,
+        application: ApplicationSettings {
+            address: "127.0.0.1:0".to_owned(),
+            grpc_address: "127.0.0.1:0".to_owned(),
+        },
+        jwt: JwtSettings {
+            secret: Secret::new("foobar".to_owned()),
+        },
+        /*
+         * How to test recaptcha:
+         * https://developers.google.com/recaptcha/docs/faq#id-like-to-run-automated-tests-with-recaptcha.-what-should-i-do
+         */
+        recaptcha: RecaptchaSettings {
+            secret: Secret::new("6LeIxAcTAAAAAGG-vFI1TnRWxMZNFuojJ4WifJWe".to_owned()),
+        },
+        database: DatabaseSettings {
+            url: Secret::new(format!("{}/{}", database_url(), database_name)),
+        },
+        stores: StoresSettings {
+            backend: store_backend(database_name),
+            purge_interval: Duration::from_secs(60),
+        },
+        email: EmailSettings {
+            // Tests inject a capturing client instead
+            client: EmailClientSettings::File,
+            sender: Email::parse(Secret::new("no-reply@example.com".to_owned()))
+                .expect("Invalid sender email"),
+            two_fa_delivery: EmailDelivery::SyncWithFallback,
+            mailbox: FileMailbox::new(
+                std::env::temp_dir().join("auth-service-test-mailbox"),
+                MailboxFormat::Eml,
+            ),
+        },
+        auth_cookie: AuthCookieSettings::local(),
+        cors: CorsSettings {
+            allowed_origins: vec![
+                AllowedOrigin::Exact("http://localhost:8000".to_owned()),
+                "https://*.example.com".parse().expect("Invalid origin"),
+            ],
+            allowed_methods: vec![Method::GET, Method::POST, Method::DELETE],
+            allowed_headers: vec![
+                AUTHORIZATION,
+                CONTENT_TYPE,
+                HeaderName::from_static(constants::CSRF_HEADER_NAME),
+            ],
+            max_age_seconds: 600,
+        },
+        health: HealthSettings {
+            timeout: Duration::from_secs(2),
+            check_email_provider: false,
+        },
+        logging: LoggingSettings {
+            format: LogFormat::Compact,
+        },
+        shutdown: ShutdownSettings {
+            readiness_delay: Duration::from_millis(500),
+            drain_timeout: Duration::from_secs(5),
+        },
+    }
+}
+
+// The Postgres server and Redis the tests run against, from the environment or `.env`
+fn database_url() -> String {
+    dotenvy::var(constants::env::DATABASE_URL_ENV_VAR).expect("DATABASE_URL must be set.")
+}
 
-    get_redis_client(redis_hostname)
-        .expect("Failed to get Redis client")
-        .get_connection()
-        .expect("Failed to get Redis connection")
+fn redis_url() -> String {
+    dotenvy::var(constants::env::REDIS_URL_ENV_VAR).unwrap_or_else(|_| {
+        let host_name =
+            dotenvy::var(constants::env::REDIS_HOST_NAME_ENV_VAR).unwrap_or("127.0.0.1".to_owned());
+        format!("redis://{}/", host_name)
+    })
+}
+
+// Redis unless STORES_BACKEND says otherwise, so the whole suite can run against Postgres
+fn store_backend(database_name: &str) -> StoreBackend {
+    match dotenvy::var(constants::env::STORES_BACKEND_ENV_VAR).as_deref() {
+        Ok("postgres") => StoreBackend::Postgres,
+        Ok("redis") | Err(_) => StoreBackend::Redis(redis_settings(database_name)),
+        Ok(other) => panic!("Invalid STORES_BACKEND: {}", other),
+    }
 }
 
-fn get_redis_client(redis_hostname: String) -> RedisResult<RedisClient> {
-    let redis_url = format!("redis://{}/", redis_hostname);
-    redis::Client::open(redis_url)
+pub fn redis_settings(database_name: &str) -> RedisSettings {
+    RedisSettings {
+        topology: RedisTopology::Standalone {
+            url: Secret::new(redis_url()),
+        },
+        // Every test app gets its own keys
+        key_prefix: format!("test:{}:", database_name),
+        timeout: Duration::from_secs(1),
+    }
+}
+
+// The banned token and 2FA code stores of `settings.stores.backend`, like main.rs builds them
+pub async fn configure_stores(
+    settings: &Settings,
+    pg_pool: &PgPool,
+) -> (
+    BannedTokenStoreType,
+    TwoFACodeStoreType,
+    Option<RedisConnection>,
+) {
+    let hasher = KeyedHasher::from_jwt_secret(&settings.jwt.secret);
+
+    match &settings.stores.backend {
+        StoreBackend::Redis(redis_settings) => {
+            let redis_connection = get_redis_connection(redis_settings)
+                .await
+                .expect("Failed to connect to Redis");
+
+            (
+                Arc::new(RedisBannedTokenStore::new(
+                    redis_connection.clone(),
+                    redis_settings.key_prefix.clone(),
+                    hasher.clone(),
+                )),
+                Arc::new(RedisTwoFACodeStore::new(
+                    redis_connection.clone(),
+                    redis_settings.key_prefix.clone(),
+                    hasher,
+                )),
+                Some(redis_connection),
+            )
+        }
+        StoreBackend::Postgres => (
+            Arc::new(PostgresBannedTokenStore::new(
+                pg_pool.clone(),
+                hasher.clone(),
+            )),
+            Arc::new(PostgresTwoFACodeStore::new(pg_pool.clone(), hasher)),
+            None,
+        ),
+    }
 }
 
-async fn configure_postgresql(db_name: &str) -> PgPool {
-    let postgresql_conn_url = constants::DATABASE_URL.expose_secret();
+pub async fn configure_postgresql(db_name: &str) -> PgPool {
+    let postgresql_conn_url = database_url();
 
     // // We are creating a new database for each test case, and we need to ensure each database has a unique name!
     // let db_name = Uuid::new_v4().to_string();
@@ -247,8 +570,8 @@ pub fn get_random_email() -> String {
     format!("{}@example.com", Uuid::new_v4())
 }
 
-async fn delete_database(db_name: &str) {
-    let postgresql_conn_url = constants::DATABASE_URL.expose_secret().to_owned();
+pub async fn delete_database(db_name: &str) {
+    let postgresql_conn_url = database_url();
     let connection_options = PgConnectOptions::from_str(&postgresql_conn_url)
         .expect("Failed to parse PostgreSQL connection string.");
     let mut connection = PgConnection::connect_with(&connection_options)
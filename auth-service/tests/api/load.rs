@@ -0,0 +1,53 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::{
+    domain::{path::Paths, Email},
+    utils::auth::generate_auth_token,
+};
+use secrecy::Secret;
+use std::time::Duration;
+use tokio::{task::JoinSet, time::timeout};
+
+const CONCURRENCY: usize = 50;
+const REQUESTS_PER_CLIENT: usize = 40;
+// Queued behind one another, the last requests of a burst would wait for all the others
+const REQUEST_DEADLINE: Duration = Duration::from_millis(500);
+const TOTAL_DEADLINE: Duration = Duration::from_secs(10);
+
+// Every verification hits Redis to check whether the token is banned. With a single blocking
+// connection behind a lock they ran one at a time, the shared async connection pipelines them.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn verify_token_should_serve_concurrent_requests() {
+    let mut app = TestApp::new().await;
+
+    let email = Email::parse(Secret::new(get_random_email())).unwrap();
+    let token = generate_auth_token(&email, &app.settings.jwt.secret).unwrap();
+    let url = format!("{}{}", &app.address, Paths::VerifyToken.as_str());
+    let client = reqwest::Client::new();
+
+    let mut clients = JoinSet::new();
+    for _ in 0..CONCURRENCY {
+        let (client, url, token) = (client.clone(), url.clone(), token.clone());
+        clients.spawn(async move {
+            for _ in 0..REQUESTS_PER_CLIENT {
+                let response = timeout(
+                    REQUEST_DEADLINE,
+                    client.post(&url).bearer_auth(&token).send(),
+                )
+                .await
+                .expect("The request took longer than its deadline")
+                .expect("Failed to execute request.");
+                assert_eq!(response.status().as_u16(), 200);
+            }
+        });
+    }
+    let all_served = async {
+        while let Some(result) = clients.join_next().await {
+            result.expect("Client task panicked");
+        }
+    };
+    timeout(TOTAL_DEADLINE, all_served)
+        .await
+        .expect("The requests took longer than their deadline");
+
+    app.clean_up().await;
+}
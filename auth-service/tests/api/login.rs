@@ -1,10 +1,7 @@
 use crate::helpers::{get_random_email, TestApp};
 use auth_service::{
-    routes::TwoFactorAuthResponse,
-    utils::constants::{
-        env::{BASE_PATH_ENV_VAR, DROPLET_IP_ENV_VAR},
-        JWT_COOKIE_NAME,
-    },
+    routes::{TokenResponse, TwoFactorAuthResponse},
+    utils::{auth::TOKEN_TTL_SECONDS, constants::JWT_COOKIE_NAME},
     ErrorResponse,
 };
 
@@ -45,8 +42,6 @@ async fn login_should_return_422_if_malformed_credentials() {
 
 #[tokio::test]
 async fn login_should_return_400_if_invalid_input() {
-    std::env::set_var(DROPLET_IP_ENV_VAR, "127.0.0.1");
-    std::env::set_var(BASE_PATH_ENV_VAR, "localhost");
     let mut app = TestApp::new().await;
     let invalid_inputs = [
         serde_json::json!({
@@ -152,6 +147,52 @@ async fn login_should_return_200_if_valid_credentials_and_2fa_disabled() {
         .expect("No auth cookie found");
 
     assert!(!auth_cookie.value().is_empty());
+    assert_eq!(
+        auth_cookie.max_age(),
+        Some(std::time::Duration::from_secs(TOKEN_TTL_SECONDS as u64)),
+        "The cookie should not outlive the token"
+    );
+
+    // Clean up database
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn login_should_return_token_in_body_if_requested() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "abcDEF123",
+        "requires2FA": false,
+        "recaptcha": "recaptcha",
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "abcDEF123",
+        "returnToken": true,
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    let auth_cookie_value = auth_cookie.value().to_owned();
+
+    let body = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse");
+
+    assert_eq!(body.token, auth_cookie_value);
 
     // Clean up database
     app.clean_up().await;
@@ -190,6 +231,9 @@ async fn login_should_return_206_if_valid_credentials_and_2fa_enabled() {
     let login_attempt_id = json_body.login_attempt_id;
     assert!(!login_attempt_id.is_empty());
 
+    let sent_email = app.last_email_to(&random_email);
+    assert!(sent_email.extract_code().is_some());
+
     // Clean up database
     app.clean_up().await;
 }
@@ -1,5 +1,5 @@
 use crate::helpers::{get_random_email, TestApp};
-use auth_service::{utils::constants::JWT_COOKIE_NAME, ErrorResponse};
+use auth_service::{routes::TokenResponse, utils::constants::JWT_COOKIE_NAME, ErrorResponse};
 use reqwest::Url;
 use secrecy::Secret;
 
@@ -53,8 +53,8 @@ async fn should_return_200_if_valid_jwt_cookie() {
     // Clean up database
     app.clean_up().await;
 
-    let banned_token_store = app.banned_token_store.read().await;
-    let contains_token = banned_token_store
+    let contains_token = app
+        .banned_token_store
         .contains_token(Secret::new(token.to_owned()))
         .await
         .expect("Failed to check if token is banned");
@@ -188,3 +188,60 @@ async fn should_return_401_if_invalid_token() {
     // Clean up database
     app.clean_up().await;
 }
+
+#[tokio::test]
+async fn should_return_200_if_valid_bearer_token() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "abcDEF123",
+        "requires2FA": false,
+        "recaptcha": "recaptcha",
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "abcDEF123",
+        "returnToken": true,
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse")
+        .token;
+
+    let response = app.post_logout_with_bearer(&token).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_logout_with_bearer(&token).await;
+
+    assert_eq!(
+        response.status().as_u16(),
+        401,
+        "A banned token should not be accepted twice"
+    );
+
+    // Clean up database
+    app.clean_up().await;
+
+    let contains_token = app
+        .banned_token_store
+        .contains_token(Secret::new(token))
+        .await
+        .expect("Failed to check if token is banned");
+
+    assert!(contains_token);
+}
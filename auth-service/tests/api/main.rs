@@ -1,8 +1,22 @@
+mod concurrency;
+mod cors;
+mod csrf;
+mod dev_mailbox;
+mod email_outbox;
+mod grpc;
+mod health;
 mod helpers;
-// mod login;
+mod load;
+mod login;
 mod logout;
+mod metrics;
+mod request_id;
 mod root;
+mod session;
+mod shutdown;
 mod signup;
+mod smtp_email_client;
+mod stores;
 mod users;
-// mod verify_2fa;
+mod verify_2fa;
 mod verify_token;
@@ -0,0 +1,77 @@
+use crate::helpers::{get_random_email, TestApp};
+
+#[tokio::test]
+async fn metrics_should_count_signups_logins_and_requests() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "abcDEF123",
+        "requires2FA": false,
+        "recaptcha": "recaptcha",
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "wrongPASS123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "abcDEF123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = app.get_metrics().await;
+
+    assert!(body.contains("auth_signups_total 1"));
+    assert!(body.contains(r#"auth_logins_total{result="success"} 1"#));
+    assert!(body.contains(r#"auth_logins_total{result="incorrect_credentials"} 1"#));
+    assert!(body.contains("auth_tokens_banned_total 1"));
+    assert!(body.contains(
+        r#"http_request_duration_seconds_count{method="POST",route="/signup",status="201"} 1"#
+    ));
+    for dependency in app.dependencies() {
+        assert!(body.contains(&format!(
+            r#"auth_dependency_up{{dependency="{}"}} 1"#,
+            dependency
+        )));
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn metrics_should_label_requests_with_the_route_pattern() {
+    let mut app = TestApp::new().await;
+
+    let response = app.delete_user(get_random_email()).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app
+        .http_client
+        .get(format!("{}/does-not-exist/123", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 404);
+
+    // The registry is per app, so it only holds the requests of this test
+    let body = app.metrics.render().expect("Failed to render metrics");
+
+    assert!(body.contains(r#"route="/users/:email""#));
+    assert!(!body.contains("/does-not-exist"));
+
+    app.clean_up().await;
+}
@@ -0,0 +1,40 @@
+use crate::helpers::TestApp;
+use auth_service::domain::path::Paths;
+
+fn request_id(response: &reqwest::Response) -> &str {
+    response
+        .headers()
+        .get("x-request-id")
+        .expect("No X-Request-Id in the response")
+        .to_str()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn should_echo_the_request_id_of_the_caller() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .get_health_with_request_id("3f2b8c1e-7d4a-4c8e-9b1a-2e5f6a7b8c9d")
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        request_id(&response),
+        "3f2b8c1e-7d4a-4c8e-9b1a-2e5f6a7b8c9d"
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_generate_a_request_id_when_missing_or_invalid() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_health(Paths::HealthLive).await;
+    assert!(uuid::Uuid::parse_str(request_id(&response)).is_ok());
+
+    let response = app.get_health_with_request_id(&"a".repeat(200)).await;
+    assert!(uuid::Uuid::parse_str(request_id(&response)).is_ok());
+
+    app.clean_up().await;
+}
@@ -0,0 +1,81 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::routes::{SessionResponse, TokenResponse};
+
+async fn signup_and_login(app: &TestApp, email: &str) -> String {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "abcDEF123",
+        "requires2FA": false,
+        "recaptcha": "recaptcha",
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "abcDEF123",
+        "returnToken": true,
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse")
+        .token
+}
+
+#[tokio::test]
+async fn should_return_session_with_cookie_or_bearer_token() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+    let token = signup_and_login(&app, &random_email).await;
+
+    for response in [
+        app.get_session().await,
+        app.get_session_with_bearer(&token).await,
+    ] {
+        assert_eq!(response.status().as_u16(), 200);
+
+        let session = response
+            .json::<SessionResponse>()
+            .await
+            .expect("Could not deserialize response body to SessionResponse");
+        assert_eq!(session.email, random_email);
+    }
+
+    // Clean up database
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_token_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_session().await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    // Clean up database
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_token_invalid_or_banned() {
+    let mut app = TestApp::new().await;
+    let token = signup_and_login(&app, &get_random_email()).await;
+
+    let response = app.get_session_with_bearer("invalid").await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.post_logout_with_bearer(&token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.get_session_with_bearer(&token).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Clean up database
+    app.clean_up().await;
+}
@@ -0,0 +1,41 @@
+use crate::helpers::TestApp;
+use auth_service::{
+    domain::path::Paths,
+    routes::{HealthStatus, ReadinessResponse},
+};
+use std::time::Duration;
+
+#[tokio::test]
+async fn should_fail_readiness_then_stop_serving() {
+    let mut app = TestApp::new().await;
+
+    app.stop.cancel();
+
+    // Still serving during the readiness delay, but no longer ready
+    let response = app.get_health(Paths::HealthReady).await;
+    assert_eq!(response.status().as_u16(), 503);
+    let body = response
+        .json::<ReadinessResponse>()
+        .await
+        .expect("Could not deserialize response body to ReadinessResponse");
+    assert_eq!(body.status, HealthStatus::Draining);
+
+    let response = app.get_health(Paths::HealthLive).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let server = app.server.take().unwrap();
+    tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .expect("The app didn't stop within the drain timeout")
+        .unwrap()
+        .expect("The app failed while shutting down");
+
+    let result = app
+        .http_client
+        .get(format!("{}{}", &app.address, Paths::HealthLive.as_str()))
+        .send()
+        .await;
+    assert!(result.is_err());
+
+    app.clean_up().await;
+}
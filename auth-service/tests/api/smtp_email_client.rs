@@ -0,0 +1,106 @@
+use crate::helpers::get_random_email;
+use auth_service::{
+    domain::{Email, EmailClient, Locale, TwoFACode},
+    services::{
+        email_templates::{EmailTemplate, TwoFACodeEmail},
+        smtp_email_client::{SmtpEmailClient, SmtpSettings, SmtpTls},
+    },
+};
+use secrecy::Secret;
+use std::time::Duration;
+
+/*
+ * These tests talk to a local MailHog instance:
+ * docker run --name mailhog -p 1025:1025 -p 8025:8025 -d mailhog/mailhog
+ */
+const MAILHOG_SMTP_PORT: u16 = 1025;
+const MAILHOG_API_PORT: u16 = 8025;
+
+fn mailhog_host() -> String {
+    std::env::var("MAILHOG_HOST").unwrap_or("127.0.0.1".to_owned())
+}
+
+fn configure_smtp_email_client() -> SmtpEmailClient {
+    let sender = Email::parse(Secret::new("no-reply@example.com".to_owned()))
+        .expect("Failed to parse sender email");
+    let settings = SmtpSettings {
+        host: mailhog_host(),
+        port: Some(MAILHOG_SMTP_PORT),
+        tls: SmtpTls::None,
+        username: None,
+        password: None,
+        timeout: Duration::from_secs(5),
+    };
+
+    SmtpEmailClient::new(sender, settings).expect("Failed to create SMTP email client")
+}
+
+async fn search_mailhog_messages(recipient: &str) -> serde_json::Value {
+    reqwest::Client::new()
+        .get(format!(
+            "http://{}:{}/api/v2/search",
+            mailhog_host(),
+            MAILHOG_API_PORT
+        ))
+        .query(&[("kind", "to"), ("query", recipient)])
+        .send()
+        .await
+        .expect("Failed to query MailHog")
+        .json()
+        .await
+        .expect("Failed to deserialize MailHog response")
+}
+
+#[tokio::test]
+async fn should_deliver_rendered_email_through_smtp() {
+    let email_client = configure_smtp_email_client();
+    let random_email = get_random_email();
+    let recipient = Email::parse(Secret::new(random_email.clone())).unwrap();
+    let code = TwoFACode::default();
+    let message = TwoFACodeEmail { code: code.clone() }
+        .render(Locale::En)
+        .unwrap();
+
+    email_client
+        .send_email(&recipient, &message)
+        .await
+        .expect("Failed to send email through SMTP");
+
+    let result = search_mailhog_messages(&random_email).await;
+
+    assert_eq!(result["total"], 1);
+
+    let item = &result["items"][0];
+    assert_eq!(
+        item["Content"]["Headers"]["Subject"][0],
+        message.subject.as_str()
+    );
+    assert!(item["Content"]["Body"]
+        .as_str()
+        .expect("Missing message body")
+        .contains(&code.to_string()));
+}
+
+#[tokio::test]
+async fn should_fail_when_smtp_server_is_unreachable() {
+    let sender = Email::parse(Secret::new("no-reply@example.com".to_owned())).unwrap();
+    let settings = SmtpSettings {
+        host: mailhog_host(),
+        port: Some(1), // Nothing listens here
+        tls: SmtpTls::None,
+        username: None,
+        password: None,
+        timeout: Duration::from_secs(1),
+    };
+    let email_client = SmtpEmailClient::new(sender, settings).unwrap();
+    let recipient = Email::parse(Secret::new(get_random_email())).unwrap();
+    let message = TwoFACodeEmail {
+        code: TwoFACode::default(),
+    }
+    .render(Locale::En)
+    .unwrap();
+
+    let result = email_client.send_email(&recipient, &message).await;
+
+    assert!(result.is_err());
+}
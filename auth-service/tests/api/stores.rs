@@ -0,0 +1,270 @@
+use crate::helpers::{
+    configure_postgresql, configure_stores, delete_database, redis_settings, test_settings,
+};
+use auth_service::{
+    app_state::{BannedTokenStoreType, TwoFACodeStoreType},
+    domain::{
+        data_stores::{BannedTokenStoreError, LoginAttemptId, TwoFACode, TwoFACodeStoreError},
+        Email,
+    },
+    services::data_stores::{HashmapTwoFACodeStore, HashsetBannedTokenStore},
+    settings::StoreBackend,
+};
+use chrono::Utc;
+use futures::future::join_all;
+use secrecy::Secret;
+use sqlx::PgPool;
+use std::{sync::Arc, time::Duration};
+use uuid::Uuid;
+
+// Every implementation of the stores, the suites below must pass on each of them
+#[derive(Debug, Clone, Copy)]
+enum Backend {
+    Memory,
+    Redis,
+    Postgres,
+}
+
+const BACKENDS: [Backend; 3] = [Backend::Memory, Backend::Redis, Backend::Postgres];
+
+// The stores of one backend, with a database and Redis keys of their own
+struct TestStores {
+    banned_token_store: BannedTokenStoreType,
+    two_fa_code_store: TwoFACodeStoreType,
+    pg_pool: PgPool,
+    database_name: String,
+}
+
+impl TestStores {
+    async fn new(backend: Backend) -> Self {
+        let database_name = Uuid::new_v4().to_string();
+        let mut settings = test_settings(&database_name);
+        let pg_pool = configure_postgresql(&database_name).await;
+
+        let (banned_token_store, two_fa_code_store): (BannedTokenStoreType, TwoFACodeStoreType) =
+            match backend {
+                Backend::Memory => (
+                    Arc::new(HashsetBannedTokenStore::default()),
+                    Arc::new(HashmapTwoFACodeStore::default()),
+                ),
+                Backend::Redis | Backend::Postgres => {
+                    settings.stores.backend = match backend {
+                        Backend::Redis => StoreBackend::Redis(redis_settings(&database_name)),
+                        _ => StoreBackend::Postgres,
+                    };
+                    let (banned_token_store, two_fa_code_store, _) =
+                        configure_stores(&settings, &pg_pool).await;
+
+                    (banned_token_store, two_fa_code_store)
+                }
+            };
+
+        Self {
+            banned_token_store,
+            two_fa_code_store,
+            pg_pool,
+            database_name,
+        }
+    }
+
+    async fn clean_up(self) {
+        self.pg_pool.close().await;
+        delete_database(&self.database_name).await;
+    }
+}
+
+fn random_token() -> Secret<String> {
+    Secret::new(Uuid::new_v4().to_string())
+}
+
+fn seconds_from_now(seconds: i64) -> usize {
+    (Utc::now().timestamp() + seconds) as usize
+}
+
+#[tokio::test]
+async fn banned_token_stores_keep_tokens_until_they_expire() {
+    join_all(BACKENDS.map(|backend| async move {
+        let stores = TestStores::new(backend).await;
+        let store = &stores.banned_token_store;
+        let token = random_token();
+
+        store
+            .add_token(token.clone(), seconds_from_now(2))
+            .await
+            .unwrap();
+        assert!(
+            store.contains_token(token.clone()).await.unwrap(),
+            "{:?}",
+            backend
+        );
+        assert!(
+            !store.contains_token(random_token()).await.unwrap(),
+            "{:?}",
+            backend
+        );
+
+        let result = store.add_token(random_token(), seconds_from_now(0)).await;
+        assert!(
+            matches!(result, Err(BannedTokenStoreError::TokenExpired)),
+            "{:?}",
+            backend
+        );
+
+        tokio::time::sleep(Duration::from_secs(3)).await;
+        assert!(!store.contains_token(token).await.unwrap(), "{:?}", backend);
+        assert!(store.purge_expired().await.is_ok(), "{:?}", backend);
+
+        stores.clean_up().await;
+    }))
+    .await;
+}
+
+#[tokio::test]
+async fn banned_token_stores_can_be_emptied() {
+    join_all(BACKENDS.map(|backend| async move {
+        let stores = TestStores::new(backend).await;
+        let store = &stores.banned_token_store;
+        let token = random_token();
+
+        store
+            .add_token(token.clone(), seconds_from_now(60))
+            .await
+            .unwrap();
+        store.empty_store().await.unwrap();
+
+        assert!(!store.contains_token(token).await.unwrap(), "{:?}", backend);
+
+        stores.clean_up().await;
+    }))
+    .await;
+}
+
+#[tokio::test]
+async fn two_fa_code_stores_verify_the_latest_code() {
+    join_all(BACKENDS.map(|backend| async move {
+        let stores = TestStores::new(backend).await;
+        let store = &stores.two_fa_code_store;
+        let email = Email::parse(Secret::new(format!("{}@example.com", Uuid::new_v4()))).unwrap();
+        let login_attempt_id = LoginAttemptId::default();
+        let code = TwoFACode::default();
+        // Defaults never start with a 0
+        let other_code = TwoFACode::parse("012345".to_owned()).unwrap();
+
+        let result = store.verify_code(&email, &login_attempt_id, &code).await;
+        assert_eq!(
+            result,
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
+            "{:?}",
+            backend
+        );
+
+        store
+            .add_code(email.clone(), login_attempt_id.clone(), code.clone())
+            .await
+            .unwrap();
+        let result = store.verify_code(&email, &login_attempt_id, &code).await;
+        assert_eq!(result, Ok(()), "{:?}", backend);
+
+        let result = store
+            .verify_code(&email, &LoginAttemptId::default(), &code)
+            .await;
+        assert_eq!(
+            result,
+            Err(TwoFACodeStoreError::IncorrectCode),
+            "{:?}",
+            backend
+        );
+        let result = store
+            .verify_code(&email, &login_attempt_id, &other_code)
+            .await;
+        assert_eq!(
+            result,
+            Err(TwoFACodeStoreError::IncorrectCode),
+            "{:?}",
+            backend
+        );
+
+        // A new login attempt replaces the pending one
+        let new_login_attempt_id = LoginAttemptId::default();
+        store
+            .add_code(
+                email.clone(),
+                new_login_attempt_id.clone(),
+                other_code.clone(),
+            )
+            .await
+            .unwrap();
+        let result = store.verify_code(&email, &login_attempt_id, &code).await;
+        assert_eq!(
+            result,
+            Err(TwoFACodeStoreError::IncorrectCode),
+            "{:?}",
+            backend
+        );
+        let result = store
+            .verify_code(&email, &new_login_attempt_id, &other_code)
+            .await;
+        assert_eq!(result, Ok(()), "{:?}", backend);
+
+        store.remove_code(&email).await.unwrap();
+        let result = store
+            .verify_code(&email, &new_login_attempt_id, &other_code)
+            .await;
+        assert_eq!(
+            result,
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
+            "{:?}",
+            backend
+        );
+
+        stores.clean_up().await;
+    }))
+    .await;
+}
+
+#[tokio::test]
+async fn postgres_stores_purge_expired_rows() {
+    let stores = TestStores::new(Backend::Postgres).await;
+    let email = Email::parse(Secret::new("purge@example.com".to_owned())).unwrap();
+    let login_attempt_id = LoginAttemptId::default();
+    let code = TwoFACode::default();
+
+    stores
+        .banned_token_store
+        .add_token(random_token(), seconds_from_now(2))
+        .await
+        .unwrap();
+    stores
+        .banned_token_store
+        .add_token(random_token(), seconds_from_now(60))
+        .await
+        .unwrap();
+    stores
+        .two_fa_code_store
+        .add_code(email.clone(), login_attempt_id.clone(), code.clone())
+        .await
+        .unwrap();
+
+    // Rather than waiting 10 minutes
+    sqlx::query("UPDATE two_fa_codes SET expires_at = NOW() - INTERVAL '1 second'")
+        .execute(&stores.pg_pool)
+        .await
+        .unwrap();
+    let result = stores
+        .two_fa_code_store
+        .verify_code(&email, &login_attempt_id, &code)
+        .await;
+    assert_eq!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    assert_eq!(stores.banned_token_store.purge_expired().await.unwrap(), 1);
+    assert_eq!(stores.two_fa_code_store.purge_expired().await.unwrap(), 1);
+
+    let remaining = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM banned_tokens")
+        .fetch_one(&stores.pg_pool)
+        .await
+        .unwrap();
+    assert_eq!(remaining, 1);
+
+    stores.clean_up().await;
+}
@@ -1,4 +1,12 @@
-use auth_service::{domain::Email, utils::constants::JWT_COOKIE_NAME};
+use auth_service::{
+    domain::{
+        data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStoreError},
+        Email,
+    },
+    routes::TwoFactorAuthResponse,
+    utils::constants::JWT_COOKIE_NAME,
+};
+use secrecy::Secret;
 
 use crate::helpers::{get_random_email, TestApp};
 
@@ -6,7 +14,6 @@ use crate::helpers::{get_random_email, TestApp};
 async fn should_return_200_if_correct_code() {
     let mut app = TestApp::new().await;
     let random_email = get_random_email();
-    let email = Email::parse(random_email.clone()).expect("Could not parse random_email to Email");
     let password = "abcDEF123".to_string();
     // Signup
     let body = serde_json::json!({
@@ -29,19 +36,21 @@ async fn should_return_200_if_correct_code() {
 
     assert_eq!(response.status().as_u16(), 206);
 
-    let (login_attempt_id, two_fa_code) = app
-        .two_fa_code_store
-        .read()
-        .await
-        .get_code(&email)
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
         .await
-        .unwrap();
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+    let two_fa_code = app
+        .last_email_to(&random_email)
+        .extract_code()
+        .expect("No 2FA code found in email");
 
     // Verify 2FA
     let body = serde_json::json!({
         "email": &random_email,
-        "2FACode": two_fa_code.as_ref(),
-        "loginAttemptId": login_attempt_id.as_ref(),
+        "2FACode": &two_fa_code,
+        "loginAttemptId": &login_attempt_id,
     });
 
     let response = app.post_verify_2fa(&body).await;
@@ -55,6 +64,17 @@ async fn should_return_200_if_correct_code() {
 
     assert!(!auth_cookie.value().is_empty());
 
+    // The code is gone once used
+    let result = app
+        .two_fa_code_store
+        .verify_code(
+            &Email::parse(Secret::new(random_email)).unwrap(),
+            &LoginAttemptId::parse(login_attempt_id).unwrap(),
+            &TwoFACode::parse(two_fa_code).unwrap(),
+        )
+        .await;
+    assert_eq!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+
     // Clean up database
     app.clean_up().await;
 }
@@ -96,7 +116,6 @@ async fn should_return_400_if_invalid_input() {
 async fn should_return_401_if_incorrect_credentials() {
     let mut app = TestApp::new().await;
     let random_email = get_random_email();
-    let email = Email::parse(random_email.clone()).expect("Could not parse random_email to Email");
     let password = "abcDEF123".to_string();
     // Signup
     let body = serde_json::json!({
@@ -119,19 +138,17 @@ async fn should_return_401_if_incorrect_credentials() {
 
     assert_eq!(response.status().as_u16(), 206);
 
-    let (login_attempt_id, _) = app
-        .two_fa_code_store
-        .read()
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
         .await
-        .get_code(&email)
-        .await
-        .unwrap();
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
 
     // Verify 2FA
     let body = serde_json::json!({
         "email": &random_email,
         "2FACode": "invalid_two_fa",
-        "loginAttemptId": login_attempt_id.as_ref(),
+        "loginAttemptId": &login_attempt_id,
     });
 
     let response = app.post_verify_2fa(&body).await;
@@ -146,7 +163,6 @@ async fn should_return_401_if_incorrect_credentials() {
 async fn should_return_401_if_old_code() {
     let mut app = TestApp::new().await;
     let random_email = get_random_email();
-    let email = Email::parse(random_email.clone()).expect("Could not parse random_email to Email");
     let password = "abcDEF123".to_string();
     // Signup
     let body = serde_json::json!({
@@ -169,13 +185,15 @@ async fn should_return_401_if_old_code() {
 
     assert_eq!(response.status().as_u16(), 206);
 
-    let (login_attempt_id, two_fa_code) = app
-        .two_fa_code_store
-        .read()
-        .await
-        .get_code(&email)
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
         .await
-        .unwrap();
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+    let two_fa_code = app
+        .last_email_to(&random_email)
+        .extract_code()
+        .expect("No 2FA code found in email");
 
     // Second Login
     let body = serde_json::json!({
@@ -190,8 +208,8 @@ async fn should_return_401_if_old_code() {
     // Verify 2FA
     let body = serde_json::json!({
         "email": &random_email,
-        "2FACode": two_fa_code.as_ref(),
-        "loginAttemptId": login_attempt_id.as_ref(),
+        "2FACode": &two_fa_code,
+        "loginAttemptId": &login_attempt_id,
     });
 
     let response = app.post_verify_2fa(&body).await;
@@ -206,7 +224,6 @@ async fn should_return_401_if_old_code() {
 async fn should_return_401_if_same_code_twice() {
     let mut app = TestApp::new().await;
     let random_email = get_random_email();
-    let email = Email::parse(random_email.clone()).expect("Could not parse random_email to Email");
     let password = "abcDEF123".to_string();
     // Signup
     let body = serde_json::json!({
@@ -229,26 +246,27 @@ async fn should_return_401_if_same_code_twice() {
 
     assert_eq!(response.status().as_u16(), 206);
 
-    let (login_attempt_id, two_fa_code) = app
-        .two_fa_code_store
-        .read()
-        .await
-        .get_code(&email)
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
         .await
-        .unwrap();
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+    let two_fa_code = app
+        .last_email_to(&random_email)
+        .extract_code()
+        .expect("No 2FA code found in email");
 
     // Verify 2FA
     let body = serde_json::json!({
         "email": &random_email,
-        "2FACode": two_fa_code.as_ref(),
-        "loginAttemptId": login_attempt_id.as_ref(),
+        "2FACode": &two_fa_code,
+        "loginAttemptId": &login_attempt_id,
     });
 
     let response = app.post_verify_2fa(&body).await;
     assert_eq!(response.status().as_u16(), 200);
 
     let response = app.post_verify_2fa(&body).await;
-    dbg!(&response.status().as_u16());
 
     assert_eq!(response.status().as_u16(), 401);
 
@@ -1,5 +1,5 @@
 use crate::helpers::{get_random_email, TestApp};
-use auth_service::{utils::constants::JWT_COOKIE_NAME, ErrorResponse};
+use auth_service::{routes::TokenResponse, utils::constants::JWT_COOKIE_NAME, ErrorResponse};
 
 #[tokio::test]
 async fn should_return_200_valid_token() {
@@ -164,3 +164,48 @@ async fn should_return_422_if_malformed_input() {
     app.clean_up().await;
     
 }
+
+#[tokio::test]
+async fn should_return_200_if_valid_bearer_token_without_body() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "abcDEF123",
+        "requires2FA": false,
+        "recaptcha": "recaptcha",
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "abcDEF123",
+        "returnToken": true,
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse")
+        .token;
+
+    let response = app.post_verify_token_with_bearer(&token).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_verify_token_with_bearer("invalid").await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Clean up database
+    app.clean_up().await;
+}